@@ -127,11 +127,23 @@ impl LocalsAccess {
 pub enum AdtAccess {
     Ref,
     MutRef,
+    /// Read through a reference or raw pointer.
     Deref,
+    /// Assign to or mutably borrow through a reference or raw pointer.
+    MutDeref,
     Plain,
     RefVariantField(VaraintFieldIdx),
     MutRefVariantField(VaraintFieldIdx),
+    /// Read a field through a reference or raw pointer.
     DerefVariantField(VaraintFieldIdx),
+    /// Assign to or mutably borrow a field through a reference or raw pointer.
+    MutDerefVariantField(VaraintFieldIdx),
+    /// Read a union field by value or through a shared reference.
+    ReadUnionField(VaraintFieldIdx),
+    /// Assign to or mutably borrow a union field.
+    WriteUnionField(VaraintFieldIdx),
+    /// Read a union field other than the one last written in the same function.
+    ReinterpretUnionField(VaraintFieldIdx),
     #[debug("Unknown({:?})", _0)]
     Unknown(Box<[ProjectionElem]>),
 }
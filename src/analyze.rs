@@ -0,0 +1,201 @@
+//! Entry points of the analysis.
+//!
+//! [`run`] analyzes the crate being compiled and is what the driver calls.
+//! [`file`] and [`source`] run the compiler in-process and return the graph
+//! without writing output, e.g. to assert on results in tests:
+//!
+//! ```ignore
+//! let src = "pub unsafe fn f() {} pub fn g() { unsafe { f() } }";
+//! let graph = analyze::source(src, &[], &Config::default(), &mut [])?;
+//! assert!(graph.edges().iter().any(|edge| edge.unsafe_));
+//! ```
+//!
+//! The caller links against `rustc_driver`, so it needs
+//! `#![feature(rustc_private)]` and the sysroot library path at runtime.
+
+use crate::{
+    analyze_fn_def,
+    config::{Config, ItemKind},
+    graph::{Graph, Node},
+    id, info_adt, info_fn,
+    model::{Adt, Function, Stub, Summary},
+    pass::Pass,
+    sink::Sink,
+    summary::Dependencies,
+    utils::{FxIndexMap, FxIndexSet},
+};
+use rustc_middle::ty::TyCtxt;
+use rustc_public::{CompilerError, CrateDef, mir::mono::Instance};
+use std::{
+    fs, io,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Analyze the local crate with custom passes and return the whole graph.
+/// Nodes are fed to the sink as soon as they're analyzed, or after passes
+/// finish if there are any, and edges after all nodes.
+pub fn run(
+    tcx: TyCtxt,
+    config: &Config,
+    sink: &mut dyn Sink,
+    passes: &mut [Box<dyn Pass>],
+) -> io::Result<Graph> {
+    let local_crate = rustc_public::local_crate();
+    let fn_defs = local_crate.fn_defs();
+
+    let mut cache_adt = Default::default();
+    sink.begin(&local_crate.name)?;
+    // Results of `Pass::finish` may go to any node, so nodes are held back.
+    let stream = passes.iter().all(|pass| !pass.finish_results());
+    let opts = &config.analysis;
+    let mut map_fn = FxIndexMap::with_capacity_and_hasher(fn_defs.len(), Default::default());
+    // Distinct callees defined outside the local crate.
+    let mut external_callees = FxIndexSet::<Instance>::default();
+    let mut graph = Graph::new(&local_crate.name);
+
+    for fn_def in fn_defs {
+        let fn_id = id::def_hash(fn_def.def_id(), tcx);
+        graph.names.insert(fn_id, fn_def.name());
+        if let Some(body) = fn_def.body() {
+            let collector = analyze_fn_def::collect(&body);
+            let finfo = info_fn::FnInfo::new(collector, &body, &mut cache_adt);
+            let results: FxIndexMap<_, _> = passes
+                .iter_mut()
+                .filter_map(|pass| {
+                    let value = pass.function(fn_def, &body, &finfo, tcx)?;
+                    Some((pass.name().to_owned(), value))
+                })
+                .collect();
+
+            if config.emits(ItemKind::Function, &fn_def.name()) {
+                let mut out_func = Function::new(fn_def, &finfo, &body, opts, tcx);
+                out_func.passes = results;
+                if stream {
+                    sink.node(Node::Function(&out_func))?;
+                }
+                graph.functions.push(out_func);
+            }
+
+            external_callees.extend(
+                finfo
+                    .callees
+                    .iter()
+                    .filter(|callee| !callee.def.krate().is_local),
+            );
+            map_fn.insert(fn_def, finfo);
+        } else if config.emits(ItemKind::Function, &fn_def.name())
+            && let Some(out_func) = Function::new_foreign(fn_def, opts, tcx)
+        {
+            if stream {
+                sink.node(Node::Function(&out_func))?;
+            }
+            graph.functions.push(out_func);
+        }
+    }
+
+    for info in map_fn.values() {
+        for callee in &info.callees {
+            graph
+                .names
+                .entry(id::callee_id(callee, tcx))
+                .or_insert_with(|| callee.name());
+        }
+    }
+
+    let mut deps = Dependencies::new(config);
+    if config.emits_kind(ItemKind::Summary) {
+        let summary = Summary::new(&local_crate.name, &map_fn, &mut deps, tcx);
+        graph.summary = Some(summary);
+    }
+
+    let map_adt = info_adt::adt_info(&map_fn);
+    for (adt, adt_info) in &map_adt {
+        graph.names.insert(id::adt_id(adt, tcx), adt.to_string(tcx));
+        if config.emits(ItemKind::Adt, &adt.to_string(tcx)) {
+            let out_adt = Adt::new(adt, adt_info, opts, tcx);
+            if stream {
+                sink.node(Node::Adt(&out_adt))?;
+            }
+            graph.adts.push(out_adt);
+        }
+    }
+
+    for callee in &external_callees {
+        if config.emits(ItemKind::Stub, &callee.name()) {
+            let stub = Stub::new(callee, &mut deps, opts, tcx);
+            if stream {
+                sink.node(Node::Stub(&stub))?;
+            }
+            graph.stubs.push(stub);
+        }
+    }
+
+    for pass in passes.iter_mut() {
+        let results = pass.finish(&graph, tcx);
+        for (id, value) in results {
+            if let Some(node_passes) = graph.passes_mut(&id) {
+                node_passes.insert(pass.name().to_owned(), value);
+            }
+        }
+    }
+    if !stream {
+        for node in graph.nodes() {
+            sink.node(node)?;
+        }
+    }
+    for edge in graph.edges() {
+        sink.edge(edge)?;
+    }
+    sink.finish(&graph)?;
+    Ok(graph)
+}
+
+/// Compile the file with rustc arguments and return the graph of the crate.
+/// `--crate-type=lib` is added unless a crate type is given. Output settings
+/// in the config are ignored, but the base directory is still used to load
+/// summaries of dependencies.
+pub fn file(
+    path: &Path,
+    rustc_args: &[&str],
+    config: &Config,
+    passes: &mut [Box<dyn Pass>],
+) -> io::Result<Graph> {
+    let mut args = vec!["rustc".to_owned(), path.display().to_string()];
+    args.extend(rustc_args.iter().map(|arg| arg.to_string()));
+    if !rustc_args.iter().any(|arg| arg.starts_with("--crate-type")) {
+        args.push("--crate-type=lib".to_owned());
+    }
+
+    let res = rustc_public::run_with_tcx!(&args, |tcx| {
+        // Stop compiling after analysis, and hand over the graph.
+        let mut no_output = Vec::<Box<dyn Sink>>::new();
+        std::ops::ControlFlow::<_, ()>::Break(run(tcx, config, &mut no_output, passes))
+    });
+    match res {
+        Err(CompilerError::Interrupted(graph)) => graph,
+        _ => Err(io::Error::other(format!(
+            "{}: compilation failed",
+            path.display()
+        ))),
+    }
+}
+
+/// Like [`file`], but the source is written to a temporary `snippet.rs`, so
+/// the crate is named `snippet` unless `--crate-name` is given.
+pub fn source(
+    src: &str,
+    rustc_args: &[&str],
+    config: &Config,
+    passes: &mut [Box<dyn Pass>],
+) -> io::Result<Graph> {
+    // Distinct per call, because analyses may run in parallel.
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNT.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("upg-{}-{n}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("snippet.rs");
+    let res = fs::write(&path, src).and_then(|()| file(&path, rustc_args, config, passes));
+    _ = fs::remove_dir_all(&dir);
+    res
+}
@@ -2,12 +2,16 @@ use crate::utils::ThinVec;
 use rustc_public::{
     CrateDef,
     mir::{
-        Body, Mutability, Place,
+        AggregateKind, Body, FieldIdx, Mutability, Operand, Place, Rvalue, Statement,
+        StatementKind, Terminator, TerminatorKind,
         visit::{Location, MirVisitor},
     },
-    ty::{RigidTy, Span, Ty, TyKind},
+    ty::{AdtDef, AdtKind, GenericArgs, RigidTy, Span, Ty, TyKind},
+};
+use std::{
+    fmt::{self, Debug},
+    ops::Range,
 };
-use std::fmt::{self, Debug};
 
 pub fn collect(body: &Body) -> Collector {
     let mut collect_types = Collector::default();
@@ -19,6 +23,8 @@ pub fn collect(body: &Body) -> Collector {
 pub struct Collector {
     pub v_ty: ThinVec<Type>,
     pub v_place: ThinVec<Place2>,
+    pub v_call: ThinVec<Call>,
+    pub v_assign: ThinVec<Assign>,
 }
 
 impl MirVisitor for Collector {
@@ -41,9 +47,39 @@ impl MirVisitor for Collector {
         self.v_place.push(Place2 {
             place: place.clone(),
             span: location.span(),
+            mutating: ptx.is_mutating(),
         });
         self.super_place(place, ptx, location);
     }
+
+    fn visit_statement(&mut self, stmt: &Statement, location: Location) {
+        let start = self.v_place.len();
+        self.super_statement(stmt, location);
+        if let StatementKind::Assign(_, rvalue) = &stmt.kind {
+            let union_init = match rvalue {
+                Rvalue::Aggregate(AggregateKind::Adt(def, _, args, _, Some(field)), _)
+                    if def.kind() == AdtKind::Union =>
+                {
+                    Some((*def, args.clone(), *field))
+                }
+                _ => None,
+            };
+            self.v_assign.push(Assign {
+                places: start..self.v_place.len(),
+                union_init,
+            });
+        }
+    }
+
+    fn visit_terminator(&mut self, term: &Terminator, location: Location) {
+        if let TerminatorKind::Call { func, .. } = &term.kind {
+            self.v_call.push(Call {
+                func: func.clone(),
+                span: term.span,
+            });
+        }
+        self.super_terminator(term, location);
+    }
 }
 
 pub struct Type {
@@ -80,13 +116,28 @@ fn ty_str(ty: &RigidTy) -> String {
     }
 }
 
+/// A call terminator. The callee is resolved from the type of `func`.
+pub struct Call {
+    pub func: Operand,
+    pub span: Span,
+}
+
+/// An assignment statement. The assigned place is visited before the rvalue,
+/// so it's the first of `places`, the indices into `v_place`.
+pub struct Assign {
+    pub places: Range<usize>,
+    /// The union and the field initialized by an aggregate like `U { a }`.
+    pub union_init: Option<(AdtDef, GenericArgs, FieldIdx)>,
+}
+
 pub struct Place2 {
     pub place: Place,
     pub span: Span,
+    /// The place is written or mutably borrowed.
+    pub mutating: bool,
 }
 
 impl Place2 {
-    #[expect(unused)]
     pub fn ty(&self, body: &Body) -> String {
         let span = self.span;
         if let Ok(ty) = self.place.ty(body.locals()) {
@@ -0,0 +1,237 @@
+//! The audit ledger: manual sign-offs of unsafe code, checked in next to the
+//! crate as `upg-audit.toml`.
+//!
+//! ```toml
+//! [[audit]]
+//! id = "bf8381ef2dd4093614fcb40d71524adc"
+//! name = "Buf::get"
+//! reviewer = "alice"
+//! date = "2026-10-19"
+//! hash = "6f1c0a2e9b7d4c35"
+//! callees = ""
+//! ```
+//!
+//! `hash` covers the `src` and `mir` of the function, and `callees` the hashes
+//! of unsafe functions it transitively calls in the crate, so an entry goes
+//! stale when either changes. Hashes depend on how MIR is printed, so they're
+//! only comparable between runs of the same toolchain with MIR and source
+//! extraction on.
+//!
+//! Unsafe functions and safe boundaries need entries. A safe boundary is a
+//! safe function calling unsafe functions or doing unsafe operations directly.
+
+use crate::{
+    graph::Graph,
+    model::Function,
+    query::NodeRef,
+    utils::{FxHashMap, FxHasher, FxIndexSet},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+/// The file discovered from the crate root.
+pub const LEDGER_FILE: &str = "upg-audit.toml";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ledger {
+    #[serde(default, rename = "audit")]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    /// The node ID.
+    pub id: String,
+    /// The display name, only for readers of the ledger.
+    #[serde(default)]
+    pub name: String,
+    pub reviewer: String,
+    pub date: String,
+    pub hash: String,
+    /// Empty if the function calls no unsafe functions in the crate.
+    #[serde(default)]
+    pub callees: String,
+}
+
+impl Ledger {
+    pub fn from_file(path: &Path) -> Result<Ledger, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path:?}: {err}"))?;
+        toml::from_str(&text).map_err(|err| format!("{path:?}: {err}"))
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Audit {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub unaudited: Vec<Unaudited>,
+    pub stale: Vec<Stale>,
+    /// Entries whose nodes are not in the graph, e.g. removed functions.
+    pub unknown: Vec<Entry>,
+}
+
+/// A function needing an entry, with hashes to record in it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Unaudited {
+    pub node: NodeRef,
+    pub safe: bool,
+    pub hash: String,
+    pub callees: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Stale {
+    pub node: NodeRef,
+    pub reviewer: String,
+    pub date: String,
+    /// The function itself changed.
+    pub source_changed: bool,
+    /// Unsafe functions it transitively calls changed.
+    pub callees_changed: bool,
+    pub hash: String,
+    pub callees: String,
+}
+
+impl Audit {
+    pub fn new(graph: &Graph, ledger: &Ledger) -> Audit {
+        let functions: FxHashMap<&str, &Function> =
+            graph.functions.iter().map(|f| (&*f.id, f)).collect();
+        let hashes: FxHashMap<&str, String> = graph
+            .functions
+            .iter()
+            .map(|f| (&*f.id, hash_function(f)))
+            .collect();
+        // Sources of unsafe edges, which are boundaries if they're safe.
+        let boundaries: FxIndexSet<&str> = graph
+            .edges()
+            .into_iter()
+            .filter(|edge| edge.unsafe_)
+            .map(|edge| edge.source)
+            .collect();
+        let entries: FxHashMap<&str, &Entry> = ledger.entries.iter().map(|e| (&*e.id, e)).collect();
+
+        let mut audit = Audit {
+            krate: graph.krate.clone(),
+            ..Default::default()
+        };
+        for f in &graph.functions {
+            if f.safe && !boundaries.contains(&*f.id) && f.unsafe_ops.is_empty() {
+                continue;
+            }
+            let hash = hashes[&*f.id].clone();
+            let callees = hash_callees(f, &functions, &hashes);
+            match entries.get(&*f.id) {
+                None => audit.unaudited.push(Unaudited {
+                    node: NodeRef::new(graph, &f.id),
+                    safe: f.safe,
+                    hash,
+                    callees,
+                }),
+                Some(entry) if entry.hash != hash || entry.callees != callees => {
+                    audit.stale.push(Stale {
+                        node: NodeRef::new(graph, &f.id),
+                        reviewer: entry.reviewer.clone(),
+                        date: entry.date.clone(),
+                        source_changed: entry.hash != hash,
+                        callees_changed: entry.callees != callees,
+                        hash,
+                        callees,
+                    })
+                }
+                Some(_) => (),
+            }
+        }
+        let unknown = ledger
+            .entries
+            .iter()
+            .filter(|e| !functions.contains_key(&*e.id));
+        audit.unknown = unknown.cloned().collect();
+        audit
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unaudited.is_empty() && self.stale.is_empty() && self.unknown.is_empty()
+    }
+
+    /// One line per finding, with hashes to record in the ledger.
+    pub fn to_text(&self) -> String {
+        let mut buf = String::new();
+        for u in &self.unaudited {
+            let kind = if u.safe { "safe boundary" } else { "unsafe fn" };
+            let (name, id, hash, callees) = (&u.node.name, &u.node.id, &u.hash, &u.callees);
+            _ = writeln!(
+                buf,
+                "unaudited {kind} {name}: id = {id:?}, hash = {hash:?}, callees = {callees:?}"
+            );
+        }
+        for s in &self.stale {
+            let changed = match (s.source_changed, s.callees_changed) {
+                (true, true) => "source and unsafe callees changed",
+                (true, false) => "source changed",
+                _ => "unsafe callees changed",
+            };
+            let (name, reviewer, date) = (&s.node.name, &s.reviewer, &s.date);
+            _ = writeln!(
+                buf,
+                "stale audit of {name} by {reviewer} on {date}: {changed}, hash = {:?}, callees = {:?}",
+                s.hash, s.callees
+            );
+        }
+        for e in &self.unknown {
+            _ = writeln!(buf, "unknown audit entry {} ({})", e.id, e.name);
+        }
+        buf
+    }
+}
+
+/// Hashes are hex strings of [`FxHasher`], which is deterministic but not
+/// cryptographic. The ledger guards against accidents, not attackers.
+fn hash_function(f: &Function) -> String {
+    let mut hasher = FxHasher::default();
+    f.src.hash(&mut hasher);
+    f.mir.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// The combined hash of unsafe functions transitively called in the crate,
+/// or empty if there are none.
+fn hash_callees(
+    f: &Function,
+    functions: &FxHashMap<&str, &Function>,
+    hashes: &FxHashMap<&str, String>,
+) -> String {
+    let mut visited = FxIndexSet::<&str>::default();
+    let mut queue = VecDeque::from([f]);
+    while let Some(caller) = queue.pop_front() {
+        for callee in &caller.callees {
+            if let Some(&g) = functions.get(&**callee)
+                && g.id != f.id
+                && visited.insert(&g.id)
+            {
+                queue.push_back(g);
+            }
+        }
+    }
+    let mut unsafe_callees: Vec<&str> = visited
+        .into_iter()
+        .filter(|id| !functions[id].safe)
+        .collect();
+    if unsafe_callees.is_empty() {
+        return String::new();
+    }
+    unsafe_callees.sort_unstable();
+    let mut hasher = FxHasher::default();
+    for id in unsafe_callees {
+        id.hash(&mut hasher);
+        hashes[id].hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
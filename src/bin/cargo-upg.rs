@@ -0,0 +1,351 @@
+//! `cargo upg [--upg-* flags] [cargo check options]` analyzes workspace members.
+//!
+//! The driver is set as `RUSTC_WORKSPACE_WRAPPER` like clippy-driver, so
+//! dependencies are compiled by the real rustc, and the driver itself passes
+//! build scripts and proc-macros through. Each member's output goes to
+//! `$UPG_DIR/crate_name`, or `crate_name-bin` and `crate_name-test` for bins
+//! and tests, and a combined workspace index is written to
+//! `$UPG_DIR/workspace.json`. `UPG_DIR` defaults to `target/upg`, and can be
+//! overridden by `--upg-dir`. It's always passed to the driver, so `dir` in
+//! `upg.toml` is ignored. Other `--upg-*` flags are forwarded to the driver
+//! via `UPG_ARGS`.
+//!
+//! Subcommands read the output without running the compiler:
+//! * `cargo upg query [--upg-dir=DIR] [--json] CRATE QUERY`: see [`Query::parse`]
+//! * `cargo upg diff [--json] BASE HEAD`: safety-relevant changes between two
+//!   crate directories, or two `UPG_DIR`s crate by crate. Exits with 1 if
+//!   there are changes and 2 on errors, so it can gate CI.
+//! * `cargo upg impact [--upg-dir=DIR] [--json] PATCH [CRATE...]`: nodes whose
+//!   spans overlap lines changed by a unified diff, and public safe functions
+//!   reaching them. PATCH is a file, or `-` for stdin.
+
+#![cfg_attr(feature = "driver", feature(rustc_private))]
+
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+use unsafety_propagation_graph::{
+    config::{self, CrateKind},
+    diff,
+    impact::{self, Impact},
+    load,
+    model::SCHEMA_VERSION,
+    query::{self, Query},
+};
+
+const DRIVER: &str = "unsafety-propagation-graph";
+
+fn main() -> ExitCode {
+    // Cargo invokes `cargo-upg upg args...`.
+    let mut args = env::args()
+        .skip(1)
+        .skip_while(|arg| arg == "upg")
+        .peekable();
+    if args.next_if_eq("query").is_some() {
+        return run_query(args.collect());
+    }
+    if args.next_if_eq("impact").is_some() {
+        return run_impact(args.collect());
+    }
+    if args.next_if_eq("diff").is_some() {
+        return run_diff(args.collect());
+    }
+
+    let mut cargo_args = Vec::new();
+    let mut upg_args = Vec::new();
+    let mut dir = env::var_os("UPG_DIR").map(PathBuf::from);
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--upg-").map(String::from) else {
+            cargo_args.push(arg);
+            continue;
+        };
+        let value = match flag.split_once('=') {
+            Some((_, value)) => Some(value.to_owned()),
+            None if !config::takes_value(&flag) => None,
+            None => args.next(),
+        };
+        if flag == "dir" || flag.starts_with("dir=") {
+            dir = value.map(PathBuf::from);
+            continue;
+        }
+        upg_args.push(arg);
+        if !flag.contains('=')
+            && let Some(value) = value
+        {
+            upg_args.push(value);
+        }
+    }
+    let cargo = cargo();
+
+    let metadata = match Metadata::new(&cargo) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            eprintln!("error: failed to read cargo metadata: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let upg_dir = dir.unwrap_or_else(|| metadata.target_directory.join("upg"));
+    // Rustc runs in a different directory for each package.
+    let upg_dir = std::path::absolute(&upg_dir).unwrap_or(upg_dir);
+
+    let status = Command::new(&cargo)
+        .arg("check")
+        .args(&cargo_args)
+        .env("RUSTC_WORKSPACE_WRAPPER", driver_path())
+        .env("UPG_DIR", &upg_dir)
+        .env("UPG_ARGS", upg_args.join("\x1f"))
+        .status();
+    match status {
+        Ok(status) if status.success() => (),
+        Ok(_) => return ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: failed to run `{cargo} check`: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let index = WorkspaceIndex::new(&metadata, &upg_dir);
+    let path = upg_dir.join("workspace.json");
+    // No member may have been analyzed to create the directory.
+    let res = fs::create_dir_all(&upg_dir)
+        .and_then(|()| fs::File::create(&path))
+        .map_err(serde_json::Error::io)
+        .and_then(|file| serde_json::to_writer_pretty(file, &index));
+    if let Err(err) = res {
+        eprintln!("error: failed to write {path:?}: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn cargo() -> String {
+    env::var("CARGO").unwrap_or_else(|_| "cargo".into())
+}
+
+/// `--upg-dir`, `UPG_DIR`, or `target/upg` of the workspace, like analysis.
+fn upg_dir(flag: Option<PathBuf>) -> Result<PathBuf, String> {
+    if let Some(dir) = flag.or_else(|| env::var_os("UPG_DIR").map(PathBuf::from)) {
+        return Ok(dir);
+    }
+    let metadata =
+        Metadata::new(&cargo()).map_err(|err| format!("failed to read cargo metadata: {err}"))?;
+    Ok(metadata.target_directory.join("upg"))
+}
+
+/// Subcommand arguments split into `--upg-dir`, the given boolean flags that
+/// are set, and positional arguments.
+type SubcommandArgs = (Option<PathBuf>, Vec<String>, Vec<String>);
+
+fn subcommand_args(args: Vec<String>, bools: &[&str]) -> Result<SubcommandArgs, String> {
+    let mut dir = None;
+    let mut set = Vec::new();
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--upg-dir=") {
+            dir = Some(PathBuf::from(value));
+        } else if arg == "--upg-dir" {
+            dir = Some(args.next().ok_or("`--upg-dir` requires a value")?.into());
+        } else if bools.contains(&arg.as_str()) {
+            set.push(arg);
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown flag `{arg}`"));
+        } else {
+            rest.push(arg);
+        }
+    }
+    Ok((dir, set, rest))
+}
+
+/// Print the output of a subcommand, or the error.
+fn report(res: Result<String, String>) -> ExitCode {
+    match res {
+        Ok(out) => {
+            print!("{out}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_query(args: Vec<String>) -> ExitCode {
+    report((|| {
+        let (dir, flags, rest) = subcommand_args(args, &["--json"])?;
+        let [krate, query @ ..] = &rest[..] else {
+            return Err("usage: cargo upg query [--upg-dir=DIR] [--json] CRATE QUERY".to_owned());
+        };
+        let query = Query::parse(query)?;
+        let graph = load::load_crate(&upg_dir(dir)?.join(krate)).map_err(|err| err.to_string())?;
+        let answer = query::run(&graph, &query)?;
+        if flags.is_empty() {
+            return Ok(answer.to_text());
+        }
+        Ok(serde_json::to_string_pretty(&answer).map_err(|err| err.to_string())? + "\n")
+    })())
+}
+
+fn run_impact(args: Vec<String>) -> ExitCode {
+    report((|| {
+        let (dir, flags, rest) = subcommand_args(args, &["--json"])?;
+        let [patch, krates @ ..] = &rest[..] else {
+            return Err(
+                "usage: cargo upg impact [--upg-dir=DIR] [--json] PATCH [CRATE...]".to_owned(),
+            );
+        };
+        let patch = if patch == "-" {
+            io::read_to_string(io::stdin())
+        } else {
+            fs::read_to_string(patch)
+        };
+        let changed = impact::changed_lines(&patch.map_err(|err| err.to_string())?);
+        let dir = upg_dir(dir)?;
+        let graphs = if krates.is_empty() {
+            load::load_all(&dir)
+        } else {
+            krates
+                .iter()
+                .map(|krate| load::load_crate(&dir.join(krate)))
+                .collect()
+        };
+        let graphs = graphs.map_err(|err| err.to_string())?;
+        let impacts = graphs.iter().map(|graph| Impact::new(graph, &changed));
+        let impacts: Vec<_> = impacts.filter(|impact| !impact.is_empty()).collect();
+        if flags.is_empty() {
+            return Ok(impacts.iter().map(Impact::to_text).collect());
+        }
+        Ok(serde_json::to_string_pretty(&impacts).map_err(|err| err.to_string())? + "\n")
+    })())
+}
+
+fn run_diff(args: Vec<String>) -> ExitCode {
+    let res = (|| {
+        let (_, flags, rest) = subcommand_args(args, &["--json"])?;
+        let [base, head] = &rest[..] else {
+            return Err("usage: cargo upg diff [--json] BASE HEAD".to_owned());
+        };
+        let diffs = diff::dirs(Path::new(base), Path::new(head)).map_err(|err| err.to_string())?;
+        let diffs: Vec<_> = diffs.into_iter().filter(|diff| !diff.is_empty()).collect();
+        let out = if flags.is_empty() {
+            diffs.iter().map(|diff| diff.to_text()).collect()
+        } else {
+            serde_json::to_string_pretty(&diffs).map_err(|err| err.to_string())? + "\n"
+        };
+        Ok((out, diffs.is_empty()))
+    })();
+    match res {
+        Ok((out, unchanged)) => {
+            print!("{out}");
+            ExitCode::from(if unchanged { 0 } else { 1 })
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// The driver is installed next to `cargo-upg`.
+fn driver_path() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    let dir = exe.parent().unwrap();
+    let mut driver = dir.join(DRIVER);
+    driver.set_extension(env::consts::EXE_EXTENSION);
+    driver
+}
+
+/// The subset of `cargo metadata` used here.
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    target_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    id: String,
+    targets: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+}
+
+impl Metadata {
+    fn new(cargo: &str) -> Result<Metadata, String> {
+        let output = Command::new(cargo)
+            .args(["metadata", "--format-version=1", "--no-deps"])
+            .output()
+            .map_err(|err| err.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+        }
+        serde_json::from_slice(&output.stdout).map_err(|err| err.to_string())
+    }
+}
+
+/// Workspace members that have been analyzed.
+#[derive(Serialize)]
+struct WorkspaceIndex {
+    schema_version: u32,
+    crates: Vec<WorkspaceCrate>,
+}
+
+#[derive(Serialize)]
+struct WorkspaceCrate {
+    package: String,
+    #[serde(rename = "crate")]
+    krate: String,
+    kind: Vec<String>,
+    /// The directory relative to `UPG_DIR`.
+    dir: String,
+}
+
+impl WorkspaceIndex {
+    fn new(metadata: &Metadata, upg_dir: &Path) -> WorkspaceIndex {
+        let mut crates = Vec::new();
+        let members = metadata
+            .packages
+            .iter()
+            .filter(|pkg| metadata.workspace_members.contains(&pkg.id));
+        for pkg in members {
+            for target in &pkg.targets {
+                if target.kind.iter().any(|kind| kind == "custom-build") {
+                    continue;
+                }
+                let krate = target.name.replace('-', "_");
+                let has_kind = |kinds: &[&str]| target.kind.iter().any(|k| kinds.contains(&&**k));
+                let kind = if has_kind(&["test", "bench"]) {
+                    CrateKind::Test
+                } else if has_kind(&["bin", "example"]) {
+                    CrateKind::Bin
+                } else {
+                    CrateKind::Lib
+                };
+                let dir = kind.dir_name(&krate);
+                if !upg_dir.join(&dir).is_dir() {
+                    continue;
+                }
+                crates.push(WorkspaceCrate {
+                    package: pkg.name.clone(),
+                    dir,
+                    krate,
+                    kind: target.kind.clone(),
+                });
+            }
+        }
+        WorkspaceIndex {
+            schema_version: SCHEMA_VERSION,
+            crates,
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{audit::LEDGER_FILE, policy::POLICY_FILE};
+use serde::{Deserialize, de::IntoDeserializer};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// The file discovered from the crate root.
+const CONFIG_FILE: &str = "upg.toml";
+
+/// Driver options. The precedence is `--upg-*` flags, then `UPG_DIR`, then
+/// `upg.toml` in the crate root. `cargo upg` always sets `UPG_DIR`, so `dir` in
+/// `upg.toml` only applies when the driver is run directly.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The base directory. Data of a crate is stored in `dir/crate_name`, see
+    /// [`CrateKind::dir_name`]. If not set, data will be printed to stdout.
+    pub dir: Option<PathBuf>,
+    pub format: Format,
+    /// Item kinds to emit. Empty means all kinds.
+    pub emit: Vec<ItemKind>,
+    /// Only emit items whose name matches any of the patterns. `*` matches any
+    /// string. Empty means all items.
+    pub include: Vec<String>,
+    /// Don't emit items whose name matches any of the patterns.
+    pub exclude: Vec<String>,
+    pub analysis: Analysis,
+    /// The audit ledger. If not set, `upg-audit.toml` in the crate root is
+    /// used if it exists. See [`crate::audit`].
+    pub audit: Option<PathBuf>,
+    /// The policy file. If not set, `upg-policy.toml` in the crate root is
+    /// used if it exists. See [`crate::policy`].
+    pub policy: Option<PathBuf>,
+    /// Report findings as `upg::*` compiler warnings. See `crate::lint`.
+    pub lints: bool,
+    /// The config file that was read, if any.
+    #[serde(skip)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Pretty JSON files, or JSON objects on stdout.
+    #[default]
+    Json,
+    /// Graphviz DOT files of the whole crate and each function, or the whole
+    /// crate on stdout.
+    Dot,
+    /// A GraphML file of the whole crate, or printed to stdout.
+    Graphml,
+    /// A SQLite database of the whole crate. The base directory is required.
+    Sqlite,
+    /// Tab-separated Datalog facts for Soufflé. The base directory is required.
+    Datalog,
+    /// Compact JSON records, one per line, streamed to `upg.ndjson` or stdout.
+    Ndjson,
+}
+
+/// Crates of a package may share a crate name, like the lib and bin of this
+/// package, so they're stored apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrateKind {
+    Lib,
+    Bin,
+    /// Built with `--test`.
+    Test,
+}
+
+impl CrateKind {
+    /// The directory of the crate under the base directory. Libs use the
+    /// crate name, which dependents look summaries up by, and others have
+    /// suffixes, which can't clash with crate names since those have no `-`.
+    pub fn dir_name(self, krate: &str) -> String {
+        match self {
+            CrateKind::Lib => krate.to_owned(),
+            CrateKind::Bin => format!("{krate}-bin"),
+            CrateKind::Test => format!("{krate}-test"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Function,
+    Adt,
+    Stub,
+    Summary,
+}
+
+/// Analysis toggles. All are on by default.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Analysis {
+    /// Pretty-print MIR for functions.
+    pub mir: bool,
+    /// Extract source code snippets.
+    pub src: bool,
+    /// Extract doc strings.
+    pub doc: bool,
+    /// Load summaries of dependency crates from the base directory.
+    pub deps: bool,
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Analysis {
+            mir: true,
+            src: true,
+            doc: true,
+            deps: true,
+        }
+    }
+}
+
+impl Config {
+    /// Strip `--upg-*` flags from rustc arguments, and merge them with `UPG_DIR`
+    /// and the config file.
+    ///
+    /// Flags:
+    /// * `--upg-config=FILE`: use the config file instead of `upg.toml` in the crate root
+    /// * `--upg-dir=DIR`: the base directory
+    /// * `--upg-format=FORMAT`: `json`, `dot`, `graphml`, `sqlite`, `datalog`, `ndjson`
+    /// * `--upg-emit=KIND,...`: `function`, `adt`, `stub`, `summary`
+    /// * `--upg-include=PATTERN` and `--upg-exclude=PATTERN`: can be repeated
+    /// * `--upg-no-mir`, `--upg-no-src`, `--upg-no-doc`, `--upg-no-deps`
+    /// * `--upg-audit=FILE`: use the audit ledger instead of `upg-audit.toml` in the crate root
+    /// * `--upg-policy=FILE`: use the policy instead of `upg-policy.toml` in the crate root
+    /// * `--upg-lints`: report findings as compiler warnings
+    ///
+    /// Flags can also be passed via `UPG_ARGS` separated by `\x1f`, which is
+    /// how `cargo upg` forwards them.
+    pub fn from_args(rustc_args: &mut Vec<String>) -> Result<Config, String> {
+        let mut flags = strip_flags(rustc_args)?;
+        if let Ok(upg_args) = env::var("UPG_ARGS") {
+            let mut upg_args: Vec<_> = upg_args
+                .split('\x1f')
+                .filter(|arg| !arg.is_empty())
+                .map(String::from)
+                .collect();
+            flags.extend(strip_flags(&mut upg_args)?);
+            if let Some(arg) = upg_args.first() {
+                return Err(format!("`{arg}` in UPG_ARGS is not a `--upg-*` flag"));
+            }
+        }
+
+        let explicit = flags.iter().find(|(name, _)| name == "config");
+        let root = crate_root(rustc_args);
+        let file = match explicit {
+            Some((_, Some(path))) => Some(PathBuf::from(path)),
+            _ => root
+                .as_ref()
+                .map(|root| root.join(CONFIG_FILE))
+                .filter(|path| path.is_file()),
+        };
+        let mut config = match file {
+            Some(file) => Config {
+                file: Some(file.clone()),
+                ..Config::from_file(&file)?
+            },
+            None => Config::default(),
+        };
+        if let Some(root) = root {
+            for (path, file) in [
+                (&mut config.audit, LEDGER_FILE),
+                (&mut config.policy, POLICY_FILE),
+            ] {
+                if path.is_none() && root.join(file).is_file() {
+                    *path = Some(root.join(file));
+                }
+            }
+        }
+        if let Some(dir) = env::var_os("UPG_DIR") {
+            config.dir = Some(dir.into());
+        }
+        for (name, value) in flags {
+            config.apply(&name, value)?;
+        }
+        let needs_dir = match config.format {
+            Format::Sqlite => Some("sqlite"),
+            Format::Datalog => Some("datalog"),
+            Format::Json | Format::Dot | Format::Graphml | Format::Ndjson => None,
+        };
+        if let Some(format) = needs_dir
+            && config.dir.is_none()
+        {
+            return Err(format!(
+                "the {format} format requires `--upg-dir` or `UPG_DIR`"
+            ));
+        }
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path:?}: {err}"))?;
+        toml::from_str(&text).map_err(|err| format!("{path:?}: {err}"))
+    }
+
+    fn apply(&mut self, name: &str, value: Option<String>) -> Result<(), String> {
+        let value = value.unwrap_or_default();
+        match name {
+            "config" => (),
+            "dir" => self.dir = Some(value.into()),
+            "format" => self.format = parse_value(&value)?,
+            "emit" => {
+                self.emit = value
+                    .split(',')
+                    .map(|kind| parse_value(kind.trim()))
+                    .collect::<Result<_, _>>()?
+            }
+            "include" => self.include.push(value),
+            "exclude" => self.exclude.push(value),
+            "no-mir" => self.analysis.mir = false,
+            "no-src" => self.analysis.src = false,
+            "no-doc" => self.analysis.doc = false,
+            "no-deps" => self.analysis.deps = false,
+            "audit" => self.audit = Some(value.into()),
+            "policy" => self.policy = Some(value.into()),
+            "lints" => self.lints = true,
+            _ => return Err(format!("unknown flag `--upg-{name}`")),
+        }
+        Ok(())
+    }
+
+    /// Files read by the driver besides the source: the config file, the audit
+    /// ledger and the policy file.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        [&self.file, &self.audit, &self.policy]
+            .into_iter()
+            .filter_map(|path| path.as_deref())
+    }
+
+    /// The directory `dir/dir_name` to store data of the crate, where the name
+    /// is from [`CrateKind::dir_name`].
+    pub fn crate_dir(&self, dir_name: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(dir_name))
+    }
+
+    /// Whether to emit items of the kind.
+    pub fn emits_kind(&self, kind: ItemKind) -> bool {
+        self.emit.is_empty() || self.emit.contains(&kind)
+    }
+
+    /// Whether to emit the item of the kind and name.
+    pub fn emits(&self, kind: ItemKind, name: &str) -> bool {
+        self.emits_kind(kind)
+            && (self.include.is_empty() || self.include.iter().any(|p| glob_match(p, name)))
+            && !self.exclude.iter().any(|p| glob_match(p, name))
+    }
+}
+
+/// Whether `--upg-name` takes a value. `--upg-no-*` and `--upg-lints` are
+/// switches.
+pub fn takes_value(name: &str) -> bool {
+    !name.starts_with("no-") && name != "lints"
+}
+
+/// Remove `--upg-*` flags from the arguments. A flag is in the form of
+/// `--upg-name=value`, `--upg-name value`, or a switch like `--upg-no-name`.
+fn strip_flags(args: &mut Vec<String>) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut flags = Vec::new();
+    let mut rest = Vec::with_capacity(args.len());
+    let mut iter = args.drain(..);
+    while let Some(arg) = iter.next() {
+        let Some(flag) = arg.strip_prefix("--upg-") else {
+            rest.push(arg);
+            continue;
+        };
+        match flag.split_once('=') {
+            Some((name, value)) => flags.push((name.to_owned(), Some(value.to_owned()))),
+            None if !takes_value(flag) => flags.push((flag.to_owned(), None)),
+            None => {
+                let value = iter.next().ok_or(format!("`{arg}` requires a value"))?;
+                flags.push((flag.to_owned(), Some(value)));
+            }
+        }
+    }
+    drop(iter);
+    *args = rest;
+    Ok(flags)
+}
+
+fn parse_value<'de, T: Deserialize<'de>>(value: &'de str) -> Result<T, String> {
+    T::deserialize(value.into_deserializer())
+        .map_err(|err: serde::de::value::Error| err.to_string())
+}
+
+/// The crate root is the cargo package directory, or the directory of the
+/// source file when rustc is invoked directly.
+fn crate_root(rustc_args: &[String]) -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("CARGO_MANIFEST_DIR") {
+        return Some(dir.into());
+    }
+    let src = rustc_args.iter().skip(1).find(|arg| arg.ends_with(".rs"))?;
+    Path::new(src).parent().map(Path::to_path_buf)
+}
+
+/// Match the name against the pattern where `*` matches any string.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<_> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` in the pattern.
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
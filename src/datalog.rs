@@ -0,0 +1,129 @@
+//! Datalog facts in tab-separated files for Soufflé.
+//!
+//! Each relation is written to `facts/<relation>.facts` under the crate
+//! directory. Example rules reproducing the built-in reports are shipped in
+//! `datalog/reports.dl`:
+//!
+//! ```sh
+//! souffle -F out/crate_name/facts -D - datalog/reports.dl
+//! ```
+//!
+//! Functions and adts are referred to by node IDs. Call sites are `caller#N`
+//! where N is the position of the call in the caller.
+
+use crate::{
+    graph::Graph,
+    model::FfiKind,
+    utils::{FxIndexMap, FxIndexSet},
+};
+use std::{fs, io, path::Path};
+
+/// Relations declared in `datalog/reports.dl`.
+const RELATIONS: &[&str] = &[
+    "fn_name",
+    "body",
+    "adt_name",
+    "unsafe_fn",
+    "public",
+    "call",
+    "site_span",
+    "in_unsafe_block",
+    "access",
+    "is_write",
+    "ctor",
+];
+
+/// Distinct rows keyed by relations in the order of [`RELATIONS`].
+pub struct Facts {
+    relations: FxIndexMap<&'static str, FxIndexSet<Vec<String>>>,
+}
+
+impl Facts {
+    pub fn new(graph: &Graph) -> Facts {
+        let mut facts = Facts {
+            relations: RELATIONS
+                .iter()
+                .map(|rel| (*rel, Default::default()))
+                .collect(),
+        };
+
+        for f in &graph.functions {
+            facts.push("fn_name", [&f.id, &f.name]);
+            // Foreign functions are declarations.
+            if f.ffi
+                .as_ref()
+                .is_none_or(|ffi| !matches!(ffi.kind, FfiKind::Import))
+            {
+                facts.push("body", [&f.id]);
+            }
+            if !f.safe {
+                facts.push("unsafe_fn", [&f.id]);
+            }
+            if f.public {
+                facts.push("public", [&f.id]);
+            }
+            for (idx, call) in f.calls.iter().enumerate() {
+                let site = format!("{}#{idx}", f.id);
+                facts.push("call", [&f.id, &call.callee, &site]);
+                facts.push("site_span", [&site, &call.span]);
+                if call.unsafe_block {
+                    facts.push("in_unsafe_block", [&site]);
+                }
+                facts.push("fn_name", [&call.callee, graph.name(&call.callee)]);
+                if call.unsafe_callee {
+                    facts.push("unsafe_fn", [&call.callee]);
+                }
+            }
+            for (adt, accesses) in &f.adts {
+                facts.push("adt_name", [adt, graph.name(adt)]);
+                for access in accesses {
+                    let field = access
+                        .variant_field()
+                        .map(|vf| vf.name())
+                        .unwrap_or_default();
+                    facts.push("access", [&f.id, adt, &field, access.kind()]);
+                    if access.is_write() {
+                        facts.push("is_write", [&f.id, adt, &field, access.kind()]);
+                    }
+                }
+            }
+        }
+        for s in &graph.stubs {
+            if !s.safe {
+                facts.push("unsafe_fn", [&s.id]);
+            }
+        }
+        for a in &graph.adts {
+            for ctor in &a.constructors {
+                facts.push("ctor", [ctor, &a.id]);
+            }
+        }
+
+        facts
+    }
+
+    fn push<const N: usize>(&mut self, relation: &str, row: [&str; N]) {
+        let row = row.iter().map(|col| sanitize(col)).collect();
+        self.relations[relation].insert(row);
+    }
+
+    /// Write `<relation>.facts` for each relation, including empty ones,
+    /// because Soufflé fails on missing input files.
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (relation, rows) in &self.relations {
+            let mut buf = String::new();
+            for row in rows {
+                buf.push_str(&row.join("\t"));
+                buf.push('\n');
+            }
+            fs::write(dir.join(format!("{relation}.facts")), buf)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tabs and newlines separate columns and rows.
+fn sanitize(col: &str) -> String {
+    col.replace(['\t', '\n'], " ")
+}
@@ -0,0 +1,243 @@
+//! Changes of the graph between two analysis runs, e.g. the base and head
+//! commits of a pull request, reported by `cargo upg diff`.
+//!
+//! Nodes are matched by IDs, which are stable as long as the definition path
+//! doesn't change. Only changes that may affect safety are reported.
+
+use crate::{
+    graph::{EdgeKind, Graph},
+    load,
+    model::{Function, safety_doc},
+    query::NodeRef,
+    utils::{FxHashMap, FxIndexSet},
+};
+use serde::Serialize;
+use std::{fmt::Write, io, path::Path};
+
+/// Compare two crate directories, or two base directories crate by crate.
+pub fn dirs(base: &Path, head: &Path) -> io::Result<Vec<Diff>> {
+    if head.join("index.json").is_file() {
+        let (base, head) = (load::load_crate(base)?, load::load_crate(head)?);
+        return Ok(vec![Diff::new(&base, &head)]);
+    }
+    let base = load::load_all(base)?;
+    let head = load::load_all(head)?;
+    let mut krates: FxIndexSet<&str> = head.iter().map(|g| &*g.krate).collect();
+    krates.extend(base.iter().map(|g| &*g.krate));
+
+    let find = |graphs: &[Graph], krate: &str| graphs.iter().position(|g| g.krate == krate);
+    let mut diffs = Vec::with_capacity(krates.len());
+    for krate in krates {
+        let empty = Graph::new(krate);
+        let base_graph = find(&base, krate).map_or(&empty, |i| &base[i]);
+        let head_graph = find(&head, krate).map_or(&empty, |i| &head[i]);
+        let mut diff = Diff::new(base_graph, head_graph);
+        diff.krate = krate.to_owned();
+        diffs.push(diff);
+    }
+    Ok(diffs)
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Diff {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Functions that are new and unsafe, or became unsafe.
+    pub added_unsafe: Vec<NodeRef>,
+    /// Unsafe functions that are removed, or became safe.
+    pub removed_unsafe: Vec<NodeRef>,
+    /// Calls to unsafe functions not in the base.
+    pub unsafe_calls: Vec<Call>,
+    /// Safe functions newly writing a field that unsafe functions access, or
+    /// writing a field that unsafe functions newly access.
+    pub safe_writers: Vec<FieldWriter>,
+    /// Unsafe functions whose `# Safety` section changed.
+    pub safety_docs: Vec<DocChange>,
+    pub constructors: Vec<ConstructorChange>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Call {
+    pub caller: NodeRef,
+    pub callee: NodeRef,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldWriter {
+    pub writer: NodeRef,
+    pub public: bool,
+    pub adt: NodeRef,
+    pub field: String,
+}
+
+/// None if the doc has no `# Safety` section.
+#[derive(Clone, Debug, Serialize)]
+pub struct DocChange {
+    pub function: NodeRef,
+    pub base: Option<String>,
+    pub head: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConstructorChange {
+    pub adt: NodeRef,
+    pub added: Vec<NodeRef>,
+    pub removed: Vec<NodeRef>,
+}
+
+impl Diff {
+    /// Compare graphs of the same crate. A crate added or removed is compared
+    /// with an empty graph.
+    pub fn new(base: &Graph, head: &Graph) -> Diff {
+        let base_fns = functions(base);
+        let head_fns = functions(head);
+        let mut diff = Diff {
+            krate: head.krate.clone(),
+            ..Default::default()
+        };
+
+        for f in head_fns.values().filter(|f| !f.safe) {
+            if base_fns.get(&*f.id).is_none_or(|b| b.safe) {
+                diff.added_unsafe.push(NodeRef::new(head, &f.id));
+            }
+        }
+        for f in base_fns.values().filter(|f| !f.safe) {
+            if head_fns.get(&*f.id).is_none_or(|h| h.safe) {
+                diff.removed_unsafe.push(NodeRef::new(base, &f.id));
+            }
+        }
+
+        let base_calls = unsafe_calls(base);
+        for (caller, callee) in unsafe_calls(head) {
+            if !base_calls.contains(&(caller, callee)) {
+                diff.unsafe_calls.push(Call {
+                    caller: NodeRef::new(head, caller),
+                    callee: NodeRef::new(head, callee),
+                });
+            }
+        }
+
+        // A writer is new if the write or the criticality of the field is new.
+        let base_writers = base.field_writers();
+        let base_critical = base.critical_fields();
+        let critical = head.critical_fields();
+        for (writer, adt, field) in head.field_writers() {
+            let adt_field = (adt, field.clone());
+            if critical.contains(&adt_field)
+                && !(base_critical.contains(&adt_field)
+                    && base_writers.contains(&(writer, adt, field.clone())))
+                && let Some(f) = head_fns.get(writer)
+                && f.safe
+            {
+                diff.safe_writers.push(FieldWriter {
+                    writer: NodeRef::new(head, writer),
+                    public: f.public,
+                    adt: NodeRef::new(head, adt),
+                    field,
+                });
+            }
+        }
+
+        for f in head_fns.values().filter(|f| !f.safe) {
+            let Some(b) = base_fns.get(&*f.id) else {
+                continue;
+            };
+            let (base_doc, head_doc) = (safety_doc(&b.doc), safety_doc(&f.doc));
+            if base_doc != head_doc {
+                diff.safety_docs.push(DocChange {
+                    function: NodeRef::new(head, &f.id),
+                    base: base_doc,
+                    head: head_doc,
+                });
+            }
+        }
+
+        let base_adts: FxHashMap<&str, _> = base.adts.iter().map(|a| (&*a.id, a)).collect();
+        for a in &head.adts {
+            let Some(b) = base_adts.get(&*a.id) else {
+                continue;
+            };
+            let added = a
+                .constructors
+                .iter()
+                .filter(|c| !b.constructors.contains(c));
+            let removed = b
+                .constructors
+                .iter()
+                .filter(|c| !a.constructors.contains(c));
+            let change = ConstructorChange {
+                adt: NodeRef::new(head, &a.id),
+                added: added.map(|c| NodeRef::new(head, c)).collect(),
+                removed: removed.map(|c| NodeRef::new(base, c)).collect(),
+            };
+            if !change.added.is_empty() || !change.removed.is_empty() {
+                diff.constructors.push(change);
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_unsafe.is_empty()
+            && self.removed_unsafe.is_empty()
+            && self.unsafe_calls.is_empty()
+            && self.safe_writers.is_empty()
+            && self.safety_docs.is_empty()
+            && self.constructors.is_empty()
+    }
+
+    /// `+` for additions, `-` for removals and `~` for changes.
+    pub fn to_text(&self) -> String {
+        let mut buf = String::new();
+        if self.is_empty() {
+            return buf;
+        }
+        _ = writeln!(buf, "crate {}", self.krate);
+        for f in &self.added_unsafe {
+            _ = writeln!(buf, "  + unsafe fn {}", f.name);
+        }
+        for f in &self.removed_unsafe {
+            _ = writeln!(buf, "  - unsafe fn {}", f.name);
+        }
+        for c in &self.unsafe_calls {
+            _ = writeln!(
+                buf,
+                "  + unsafe call {} -> {}",
+                c.caller.name, c.callee.name
+            );
+        }
+        for w in &self.safe_writers {
+            let vis = if w.public { "pub " } else { "" };
+            let (writer, adt, field) = (&w.writer.name, &w.adt.name, &w.field);
+            _ = writeln!(buf, "  + {vis}safe fn {writer} writes {adt}.{field}");
+        }
+        for d in &self.safety_docs {
+            let change = match (&d.base, &d.head) {
+                (None, _) => "added",
+                (_, None) => "removed",
+                _ => "changed",
+            };
+            _ = writeln!(buf, "  ~ safety doc of {} {change}", d.function.name);
+        }
+        for c in &self.constructors {
+            let added = c.added.iter().map(|f| format!(" +{}", f.name));
+            let removed = c.removed.iter().map(|f| format!(" -{}", f.name));
+            let changes: String = added.chain(removed).collect();
+            _ = writeln!(buf, "  ~ constructors of {}:{changes}", c.adt.name);
+        }
+        buf
+    }
+}
+
+fn functions(graph: &Graph) -> FxHashMap<&str, &Function> {
+    graph.functions.iter().map(|f| (&*f.id, f)).collect()
+}
+
+fn unsafe_calls(graph: &Graph) -> FxIndexSet<(&str, &str)> {
+    graph
+        .edges()
+        .into_iter()
+        .filter(|edge| matches!(edge.kind, EdgeKind::Call) && edge.unsafe_)
+        .map(|edge| (edge.source, edge.target))
+        .collect()
+}
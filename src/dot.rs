@@ -0,0 +1,161 @@
+//! Graphviz DOT rendering.
+//!
+//! Nodes are clustered by modules. Unsafe functions are filled in red, and
+//! unsafe edges are bold red. Call edges are solid, and access edges are dashed
+//! with access kinds as labels.
+
+use crate::{
+    graph::{Edge, EdgeKind, Graph, Node},
+    utils::{FxHashMap, FxIndexMap, FxIndexSet},
+};
+use std::fmt::Write;
+
+const UNSAFE_COLOR: &str = "#cc0000";
+const UNSAFE_FILL: &str = "#f4cccc";
+const ADT_COLOR: &str = "#3c78d8";
+
+/// Render the whole crate.
+pub fn render(graph: &Graph) -> String {
+    render_edges(graph, &graph.node_map(), &graph.krate, graph.edges(), true)
+}
+
+/// Render the neighbourhood of a function: its callers, callees and adts.
+/// `nodes` is [`Graph::node_map`], and `edges` are the edges from or to the
+/// function.
+pub fn render_neighbourhood(
+    graph: &Graph,
+    nodes: &FxHashMap<&str, Node>,
+    id: &str,
+    edges: Vec<Edge>,
+) -> String {
+    render_edges(graph, nodes, graph.name(id), edges, false)
+}
+
+/// Nodes on edges are always rendered. Other nodes are rendered if `all_nodes` is true.
+fn render_edges(
+    graph: &Graph,
+    nodes: &FxHashMap<&str, Node>,
+    title: &str,
+    edges: Vec<Edge>,
+    all_nodes: bool,
+) -> String {
+    let mut buf = String::new();
+    _ = writeln!(buf, "digraph {} {{", quote(title));
+    buf.push_str("  rankdir=LR;\n  node [shape=box, fontname=\"monospace\"];\n");
+    buf.push_str("  edge [fontname=\"monospace\", fontsize=10];\n");
+
+    let mut ids: FxIndexSet<&str> = if all_nodes {
+        graph.nodes().map(Node::id).collect()
+    } else {
+        FxIndexSet::default()
+    };
+    for edge in &edges {
+        ids.insert(edge.source);
+        ids.insert(edge.target);
+    }
+
+    let mut modules = FxIndexMap::<&str, Vec<Node>>::default();
+    let mut missing = Vec::new();
+    for id in ids {
+        match nodes.get(id) {
+            Some(node) => modules.entry(node.module()).or_default().push(*node),
+            None => missing.push(id),
+        }
+    }
+    for (idx, (module, nodes)) in modules.iter().enumerate() {
+        _ = writeln!(buf, "  subgraph cluster_{idx} {{");
+        _ = writeln!(buf, "    label={};", quote(module));
+        for node in nodes {
+            _ = writeln!(buf, "    {} [{}];", quote(node.id()), node_attrs(*node));
+        }
+        buf.push_str("  }\n");
+    }
+    // Targets that are not emitted, e.g. excluded by filters.
+    for id in missing {
+        let label = quote(graph.name(id));
+        _ = writeln!(buf, "  {} [label={label}, style=dotted];", quote(id));
+    }
+
+    // Accesses between the same pair of nodes are merged into one edge.
+    let mut accesses = FxIndexMap::<(&str, &str), (Vec<String>, bool)>::default();
+    for edge in &edges {
+        match edge.kind {
+            EdgeKind::Call => {
+                let style = if edge.unsafe_ {
+                    format!(" [color=\"{UNSAFE_COLOR}\", penwidth=2]")
+                } else {
+                    String::new()
+                };
+                _ = writeln!(
+                    buf,
+                    "  {} -> {}{style};",
+                    quote(edge.source),
+                    quote(edge.target)
+                );
+            }
+            EdgeKind::Access(access) => {
+                let (labels, unsafe_) = accesses.entry((edge.source, edge.target)).or_default();
+                let label = match access.variant_field() {
+                    Some(vf) => format!("{}({})", access.kind(), vf.name()),
+                    None => access.kind().to_owned(),
+                };
+                labels.push(label);
+                *unsafe_ |= edge.unsafe_;
+            }
+        }
+    }
+    for ((source, target), (labels, unsafe_)) in accesses {
+        let color = if unsafe_ { UNSAFE_COLOR } else { ADT_COLOR };
+        let width = if unsafe_ { ", penwidth=2" } else { "" };
+        _ = writeln!(
+            buf,
+            "  {} -> {} [style=dashed, color=\"{color}\", label={}{width}];",
+            quote(source),
+            quote(target),
+            quote(&labels.join("\n"))
+        );
+    }
+
+    buf.push_str("}\n");
+    buf
+}
+
+fn node_attrs(node: Node) -> String {
+    let mut attrs = format!("label={}", quote(node.name()));
+    if !node.span().is_empty() {
+        _ = write!(attrs, ", tooltip={}", quote(node.span()));
+    }
+    let mut style = Vec::new();
+    match node {
+        Node::Adt(_) => _ = write!(attrs, ", shape=ellipse, color=\"{ADT_COLOR}\""),
+        Node::Stub(_) => style.push("dashed"),
+        Node::Function(_) => (),
+    }
+    if node.safe() == Some(false) {
+        style.extend(["filled", "bold"]);
+        _ = write!(
+            attrs,
+            ", color=\"{UNSAFE_COLOR}\", fillcolor=\"{UNSAFE_FILL}\""
+        );
+    }
+    if !style.is_empty() {
+        _ = write!(attrs, ", style={}", quote(&style.join(",")));
+    }
+    attrs
+}
+
+/// A double-quoted DOT string.
+fn quote(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+    buf
+}
@@ -0,0 +1,233 @@
+//! The rustc driver. It runs as `rustc` directly, or as
+//! `RUSTC_WORKSPACE_WRAPPER` under cargo.
+
+use crate::{
+    analyze,
+    audit::{Audit, Ledger},
+    config::{Config, CrateKind},
+    lint::{self, Lints},
+    pass::Pass,
+    policy::{self, Policy},
+    schema, sink, utils,
+};
+use rustc_driver::Compilation;
+use rustc_interface::interface;
+use rustc_lint::LintStore;
+use rustc_middle::ty::TyCtxt;
+use rustc_public::rustc_internal;
+use rustc_session::{Session, config::CrateType};
+use rustc_span::Symbol;
+use std::{fs, io, path::Path, process::Command};
+
+/// Run the driver with custom passes. This is the whole `main` of the
+/// `unsafety-propagation-graph` binary, which has no custom passes. Lints
+/// are run as a pass with `--upg-lints`.
+pub fn main(mut passes: Vec<Box<dyn Pass>>) {
+    let mut rustc_args: Vec<_> = std::env::args().collect();
+
+    if let Some(root) = rustc_args
+        .get(1)
+        .and_then(|arg| arg.strip_prefix("--upg-export-schema="))
+    {
+        if let Err(err) = schema::export(Path::new(root)) {
+            eprintln!("error: failed to export schema: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Cargo invokes `RUSTC_WORKSPACE_WRAPPER` as `driver path/to/rustc args...`.
+    let wrapper =
+        rustc_args.get(1).map(Path::new).and_then(Path::file_stem) == Some("rustc".as_ref());
+    if wrapper {
+        let rustc = rustc_args.remove(1);
+        if passthrough(&rustc_args[1..]) {
+            let status = Command::new(rustc).args(&rustc_args[1..]).status();
+            std::process::exit(status.ok().and_then(|s| s.code()).unwrap_or(1));
+        }
+    }
+
+    let config = match Config::from_args(&mut rustc_args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+    let ledger = match config.audit.as_deref().map(Ledger::from_file).transpose() {
+        Ok(ledger) => ledger,
+        Err(err) => {
+            eprintln!("error: failed to read the audit ledger: {err}");
+            std::process::exit(1);
+        }
+    };
+    let policy = match config.policy.as_deref().map(Policy::from_file).transpose() {
+        Ok(policy) => policy,
+        Err(err) => {
+            eprintln!("error: failed to read the policy: {err}");
+            std::process::exit(1);
+        }
+    };
+    let checks = Checks {
+        ledger: ledger.as_ref(),
+        policy: policy.as_ref(),
+    };
+
+    // The `upg` lint tool is registered for the crate, and `cfg(upg)` lets
+    // code allow lints only under the driver. See [`crate::lint`].
+    if config.lints {
+        rustc_args.extend([
+            "--cfg=upg".to_owned(),
+            "-Zcrate-attr=feature(register_tool)".to_owned(),
+            "-Zcrate-attr=register_tool(upg)".to_owned(),
+        ]);
+        // Cargo checks cfgs, and the driver's cfg is not known to it.
+        if rustc_args.iter().any(|arg| arg.starts_with("--check-cfg")) {
+            rustc_args.push("--check-cfg=cfg(upg)".to_owned());
+        }
+        passes.push(Box::new(Lints::default()));
+    }
+
+    let mut callbacks = Callbacks {
+        config: &config,
+        checks: &checks,
+        wrapper,
+        passes: &mut passes,
+        result: Ok(false),
+    };
+    let res = rustc_driver::catch_fatal_errors(|| {
+        rustc_driver::run_compiler(&rustc_args, &mut callbacks);
+    });
+    let violated = match callbacks.result {
+        Ok(violated) => violated,
+        Err(err) => {
+            eprintln!("error: failed to write output: {err}");
+            std::process::exit(1);
+        }
+    };
+    if violated {
+        eprintln!("error: upg: the policy is violated");
+    }
+    if violated || res.is_err() {
+        std::process::exit(1);
+    }
+}
+
+/// Checks of the graph against checked-in files.
+struct Checks<'a> {
+    ledger: Option<&'a Ledger>,
+    policy: Option<&'a Policy>,
+}
+
+struct Callbacks<'a> {
+    config: &'a Config,
+    checks: &'a Checks<'a>,
+    wrapper: bool,
+    passes: &'a mut [Box<dyn Pass>],
+    /// Whether the policy is violated.
+    result: io::Result<bool>,
+}
+
+impl rustc_driver::Callbacks for Callbacks<'_> {
+    fn config(&mut self, config: &mut interface::Config) {
+        // Cargo doesn't know that the output depends on these, so checked
+        // crates would be fresh after changing them. Clippy does the same for
+        // `CLIPPY_ARGS` and `clippy.toml`.
+        let files: Vec<_> = self
+            .config
+            .files()
+            .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.to_owned()))
+            .collect();
+        config.psess_created = Some(Box::new(move |psess| {
+            let env = psess.env_depinfo.get_mut();
+            for var in ["UPG_DIR", "UPG_ARGS"] {
+                let value = std::env::var(var).ok().map(|value| Symbol::intern(&value));
+                env.insert((Symbol::intern(var), value));
+            }
+            let file_depinfo = psess.file_depinfo.get_mut();
+            for file in files {
+                file_depinfo.insert(Symbol::intern(&file.to_string_lossy()));
+            }
+        }));
+        if self.config.lints {
+            config.register_lints = Some(Box::new(|_: &Session, store: &mut LintStore| {
+                store.register_lints(lint::LINTS);
+            }));
+        }
+    }
+
+    fn after_analysis(&mut self, _: &interface::Compiler, tcx: TyCtxt<'_>) -> Compilation {
+        self.result =
+            rustc_internal::run(tcx, || run(tcx, self.config, self.checks, self.passes)).unwrap();
+        // In wrapper mode, compilation continues after analysis, because cargo
+        // expects the metadata to check dependent crates.
+        if self.wrapper && self.result.is_ok() {
+            Compilation::Continue
+        } else {
+            Compilation::Stop
+        }
+    }
+}
+
+/// Build scripts, proc-macros and queries like `rustc -vV` are handled by the
+/// real rustc without analysis.
+fn passthrough(args: &[String]) -> bool {
+    let value_of = |flag: &str| {
+        let pos = args.iter().position(|arg| arg == flag)?;
+        args.get(pos + 1).map(String::as_str)
+    };
+    value_of("--crate-name") == Some("build_script_build")
+        || value_of("--crate-type") == Some("proc-macro")
+        || args
+            .iter()
+            .any(|arg| arg == "-vV" || arg == "-V" || arg.starts_with("--print"))
+}
+
+/// Analyze the crate and check the graph. Returns whether the policy is
+/// violated.
+fn run(
+    tcx: TyCtxt,
+    config: &Config,
+    checks: &Checks,
+    passes: &mut [Box<dyn Pass>],
+) -> io::Result<bool> {
+    let krate = rustc_public::local_crate().name;
+    let kind = if tcx.sess.opts.test {
+        CrateKind::Test
+    } else if tcx.crate_types().contains(&CrateType::Executable) {
+        CrateKind::Bin
+    } else {
+        CrateKind::Lib
+    };
+    let dir_name = kind.dir_name(&krate);
+    let mut sink = sink::new(config, &dir_name)?;
+    let graph = analyze::run(tcx, config, &mut sink, passes)?;
+
+    // Findings are warnings on stderr, and `audit.json` next to the output.
+    if let Some(ledger) = checks.ledger {
+        let audit = Audit::new(&graph, ledger);
+        for line in audit.to_text().lines() {
+            eprintln!("warning: upg: {line}");
+        }
+        if let Some(dir) = config.crate_dir(&dir_name) {
+            let json = serde_json::to_string_pretty(&audit)?;
+            let path = dir.join("audit.json");
+            fs::write(&path, json).map_err(utils::with_path(&path))?;
+        }
+    }
+    // Violations are errors, and `policy.json` lists them.
+    let mut violated = false;
+    if let Some(policy) = checks.policy {
+        let violations = policy.check(&graph);
+        for line in policy::to_text(&violations).lines() {
+            eprintln!("error: upg: {line}");
+        }
+        if let Some(dir) = config.crate_dir(&dir_name) {
+            let json = serde_json::to_string_pretty(&violations)?;
+            let path = dir.join("policy.json");
+            fs::write(&path, json).map_err(utils::with_path(&path))?;
+        }
+        violated = !violations.is_empty();
+    }
+    Ok(violated)
+}
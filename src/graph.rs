@@ -0,0 +1,206 @@
+//! The whole-crate graph assembled from output nodes.
+//!
+//! Sinks receive nodes and edges as views here, and formats rendering the
+//! graph at once share them too.
+
+use crate::{
+    model::{Adt, AdtAccess, Function, Stub, Summary},
+    utils::{FxHashMap, FxIndexMap, FxIndexSet},
+};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+#[derive(Debug, Default)]
+pub struct Graph {
+    pub krate: String,
+    pub functions: Vec<Function>,
+    pub adts: Vec<Adt>,
+    pub stubs: Vec<Stub>,
+    /// Display names of all nodes and edge targets, including those not emitted.
+    pub names: FxIndexMap<String, String>,
+    /// None if summaries are not emitted.
+    pub summary: Option<Summary>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Node<'a> {
+    Function(&'a Function),
+    Adt(&'a Adt),
+    Stub(&'a Stub),
+}
+
+impl<'a> Node<'a> {
+    pub fn id(self) -> &'a str {
+        match self {
+            Node::Function(f) => &f.id,
+            Node::Adt(a) => &a.id,
+            Node::Stub(s) => &s.id,
+        }
+    }
+
+    pub fn name(self) -> &'a str {
+        match self {
+            Node::Function(f) => &f.name,
+            Node::Adt(a) => &a.name,
+            Node::Stub(s) => &s.name,
+        }
+    }
+
+    pub fn module(self) -> &'a str {
+        match self {
+            Node::Function(f) => &f.module,
+            Node::Adt(a) => &a.module,
+            Node::Stub(s) => &s.module,
+        }
+    }
+
+    /// None for adts.
+    pub fn safe(self) -> Option<bool> {
+        match self {
+            Node::Function(f) => Some(f.safe),
+            Node::Adt(_) => None,
+            Node::Stub(s) => Some(s.safe),
+        }
+    }
+
+    pub fn kind(self) -> &'static str {
+        match self {
+            Node::Function(_) => "function",
+            Node::Adt(_) => "adt",
+            Node::Stub(_) => "stub",
+        }
+    }
+
+    /// Stubs have no span, because they're not analyzed.
+    pub fn span(self) -> &'a str {
+        match self {
+            Node::Function(f) => &f.span,
+            Node::Adt(a) => &a.span,
+            Node::Stub(_) => "",
+        }
+    }
+}
+
+/// Serialized as the node itself.
+impl Serialize for Node<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Node::Function(f) => f.serialize(serializer),
+            Node::Adt(a) => a.serialize(serializer),
+            Node::Stub(s) => s.serialize(serializer),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Edge<'a> {
+    pub source: &'a str,
+    pub target: &'a str,
+    pub kind: EdgeKind<'a>,
+    /// Calls to unsafe functions, or unsafe accesses like reading union fields.
+    pub unsafe_: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum EdgeKind<'a> {
+    Call,
+    Access(&'a AdtAccess),
+}
+
+impl Graph {
+    pub fn new(krate: &str) -> Graph {
+        Graph {
+            krate: krate.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = Node<'_>> {
+        let functions = self.functions.iter().map(Node::Function);
+        let adts = self.adts.iter().map(Node::Adt);
+        let stubs = self.stubs.iter().map(Node::Stub);
+        functions.chain(adts).chain(stubs)
+    }
+
+    /// Nodes keyed by IDs.
+    pub fn node_map(&self) -> FxHashMap<&str, Node<'_>> {
+        self.nodes().map(|node| (node.id(), node)).collect()
+    }
+
+    /// Writers, adts and field names of accesses that may write fields.
+    pub fn field_writers(&self) -> FxIndexSet<(&str, &str, String)> {
+        let mut writers = FxIndexSet::default();
+        for f in &self.functions {
+            for (adt, accesses) in &f.adts {
+                for access in accesses.iter().filter(|access| access.is_write()) {
+                    if let Some(vf) = access.variant_field() {
+                        writers.insert((&*f.id, &**adt, vf.name()));
+                    }
+                }
+            }
+        }
+        writers
+    }
+
+    /// Adts and field names accessed by unsafe functions, whose values their
+    /// safety may depend on.
+    pub fn critical_fields(&self) -> FxIndexSet<(&str, String)> {
+        let mut fields = FxIndexSet::default();
+        for f in self.functions.iter().filter(|f| !f.safe) {
+            for (adt, accesses) in &f.adts {
+                for vf in accesses.iter().filter_map(|access| access.variant_field()) {
+                    fields.insert((&**adt, vf.name()));
+                }
+            }
+        }
+        fields
+    }
+
+    /// Results of custom passes in the node.
+    pub fn passes_mut(&mut self, id: &str) -> Option<&mut FxIndexMap<String, Value>> {
+        if let Some(f) = self.functions.iter_mut().find(|f| f.id == id) {
+            return Some(&mut f.passes);
+        }
+        if let Some(a) = self.adts.iter_mut().find(|a| a.id == id) {
+            return Some(&mut a.passes);
+        }
+        let s = self.stubs.iter_mut().find(|s| s.id == id)?;
+        Some(&mut s.passes)
+    }
+
+    /// The display name of an ID, which falls back to the ID itself.
+    pub fn name<'a>(&'a self, id: &'a str) -> &'a str {
+        self.names.get(id).map_or(id, String::as_str)
+    }
+
+    /// Call edges and access edges from functions. Targets may be missing in
+    /// nodes if they're not emitted.
+    pub fn edges(&self) -> Vec<Edge<'_>> {
+        let nodes = self.node_map();
+        let mut edges = Vec::new();
+        for func in &self.functions {
+            for callee in &func.callees {
+                let unsafe_ = func.unsafe_callee(callee).unwrap_or_else(|| {
+                    nodes.get(callee.as_str()).and_then(|n| n.safe()) == Some(false)
+                });
+                edges.push(Edge {
+                    source: &func.id,
+                    target: callee,
+                    kind: EdgeKind::Call,
+                    unsafe_,
+                });
+            }
+            for (adt, accesses) in &func.adts {
+                for access in accesses {
+                    edges.push(Edge {
+                        source: &func.id,
+                        target: adt,
+                        kind: EdgeKind::Access(access),
+                        unsafe_: access.is_unsafe(),
+                    });
+                }
+            }
+        }
+        edges
+    }
+}
@@ -0,0 +1,155 @@
+//! GraphML rendering of the whole crate for desktop graph tools like Gephi
+//! and yEd.
+//!
+//! Nodes are functions, stubs, adts and fields, typed by the `kind` attribute.
+//! A field node is created for each accessed field, and linked to its adt by a
+//! `field` edge. Accesses to fields point to field nodes instead of adts.
+
+use crate::{
+    graph::{EdgeKind, Graph},
+    model::AdtAccess,
+    utils::FxIndexMap,
+};
+use std::fmt::Write;
+
+/// (id, for, name, type)
+const KEYS: &[[&str; 4]] = &[
+    ["label", "node", "label", "string"],
+    ["n_kind", "node", "kind", "string"],
+    ["module", "node", "module", "string"],
+    ["safe", "node", "safe", "boolean"],
+    ["span", "node", "span", "string"],
+    ["e_kind", "edge", "kind", "string"],
+    ["access", "edge", "access", "string"],
+    ["unsafe", "edge", "unsafe", "boolean"],
+];
+
+/// Node attributes in the order of [`KEYS`]. None means the attribute is absent.
+type NodeData = [Option<String>; 5];
+
+pub fn render(graph: &Graph) -> String {
+    let mut nodes = FxIndexMap::<String, NodeData>::default();
+    for node in graph.nodes() {
+        let data = [
+            Some(node.name().to_owned()),
+            Some(node.kind().to_owned()),
+            Some(node.module().to_owned()),
+            node.safe().map(|safe| safe.to_string()),
+            Some(node.span().to_owned()).filter(|span| !span.is_empty()),
+        ];
+        nodes.insert(node.id().to_owned(), data);
+    }
+
+    // (source, target, [kind, access, unsafe])
+    let mut edges = Vec::<(String, String, [Option<String>; 3])>::new();
+    for edge in graph.edges() {
+        let (target, kind, access) = match edge.kind {
+            EdgeKind::Call => (edge.target.to_owned(), "call", None),
+            EdgeKind::Access(access) => {
+                let target = field_node(graph, &mut nodes, &mut edges, edge.target, access);
+                (target, "access", Some(access.kind().to_owned()))
+            }
+        };
+        if !nodes.contains_key(&target) {
+            // Targets that are not emitted, e.g. excluded by filters.
+            let kind = if kind == "call" { "function" } else { "adt" };
+            let name = graph.name(&target).to_owned();
+            nodes.insert(
+                target.clone(),
+                [Some(name), Some(kind.to_owned()), None, None, None],
+            );
+        }
+        let data = [
+            Some(kind.to_owned()),
+            access,
+            Some(edge.unsafe_.to_string()),
+        ];
+        edges.push((edge.source.to_owned(), target, data));
+    }
+
+    let mut buf = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://graphml.graphdrawing.org/xmlns \
+         http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd\">\n",
+    );
+    for [id, for_, name, ty] in KEYS {
+        _ = writeln!(
+            buf,
+            "  <key id=\"{id}\" for=\"{for_}\" attr.name=\"{name}\" attr.type=\"{ty}\"/>"
+        );
+    }
+    _ = writeln!(
+        buf,
+        "  <graph id=\"{}\" edgedefault=\"directed\">",
+        escape(&graph.krate)
+    );
+    for (id, data) in &nodes {
+        _ = writeln!(buf, "    <node id=\"{}\">", escape(id));
+        write_data(&mut buf, &KEYS[..5], data);
+        buf.push_str("    </node>\n");
+    }
+    for (idx, (source, target, data)) in edges.iter().enumerate() {
+        _ = writeln!(
+            buf,
+            "    <edge id=\"e{idx}\" source=\"{}\" target=\"{}\">",
+            escape(source),
+            escape(target)
+        );
+        write_data(&mut buf, &KEYS[5..], data);
+        buf.push_str("    </edge>\n");
+    }
+    buf.push_str("  </graph>\n</graphml>\n");
+    buf
+}
+
+/// Returns the field node for field accesses, or the adt otherwise. The field
+/// node and its `field` edge to the adt are added on first use.
+fn field_node(
+    graph: &Graph,
+    nodes: &mut FxIndexMap<String, NodeData>,
+    edges: &mut Vec<(String, String, [Option<String>; 3])>,
+    adt: &str,
+    access: &AdtAccess,
+) -> String {
+    let Some(vf) = access.variant_field() else {
+        return adt.to_owned();
+    };
+    let idx = |i: Option<u32>| i.map(|i| i.to_string()).unwrap_or_default();
+    let id = format!("{adt}:{}:{}", idx(vf.variant), idx(vf.field));
+    if !nodes.contains_key(&id) {
+        let module = nodes.get(adt).and_then(|data| data[2].clone());
+        let name = format!("{}.{}", graph.name(adt), vf.name());
+        nodes.insert(
+            id.clone(),
+            [Some(name), Some("field".to_owned()), module, None, None],
+        );
+        let data = [Some("field".to_owned()), None, Some(false.to_string())];
+        edges.push((id.clone(), adt.to_owned(), data));
+    }
+    id
+}
+
+fn write_data(buf: &mut String, keys: &[[&str; 4]], data: &[Option<String>]) {
+    for ([key, ..], value) in keys.iter().zip(data) {
+        if let Some(value) = value {
+            _ = writeln!(buf, "      <data key=\"{key}\">{}</data>", escape(value));
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            '\'' => buf.push_str("&apos;"),
+            c => buf.push(c),
+        }
+    }
+    buf
+}
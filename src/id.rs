@@ -0,0 +1,52 @@
+//! Stable node identifiers.
+//!
+//! An ID is the fixed-width hex string of the def path hash, suffixed by a hash
+//! of generic args if any, e.g. `<def hash>-<args hash>`. It doesn't depend on
+//! display names, so it's collision-free and safe as a file name. IDs are stable
+//! across runs of the same toolchain.
+
+use crate::adt::Adt;
+use rustc_data_structures::{fingerprint::Fingerprint, stable_hasher::StableHasher};
+use rustc_middle::ty::{GenericArgsRef, TyCtxt, print::with_no_trimmed_paths};
+use rustc_public::{CrateDef, DefId, mir::mono::Instance, rustc_internal::internal};
+use std::hash::Hash;
+
+/// The ID of a definition regardless of generic args. This is also the key of
+/// functions in cross-crate summaries.
+pub fn def_hash(def_id: DefId, tcx: TyCtxt) -> String {
+    let did = internal(tcx, def_id);
+    let (a, b) = tcx.def_path_hash(did).0.split();
+    format!("{:016x}{:016x}", a.as_u64(), b.as_u64())
+}
+
+/// The ID of a monomorphized function.
+pub fn instance_id(instance: &Instance, tcx: TyCtxt) -> String {
+    let args = internal(tcx, instance).args;
+    with_args(def_hash(instance.def.def_id(), tcx), args)
+}
+
+/// The ID that a callee edge points to. Local functions are emitted once for
+/// all instantiations, while an external callee is emitted as a stub per instance.
+pub fn callee_id(instance: &Instance, tcx: TyCtxt) -> String {
+    if instance.def.krate().is_local {
+        def_hash(instance.def.def_id(), tcx)
+    } else {
+        instance_id(instance, tcx)
+    }
+}
+
+/// The ID of a monomorphized adt.
+pub fn adt_id(adt: &Adt, tcx: TyCtxt) -> String {
+    let args = internal(tcx, &adt.args);
+    with_args(def_hash(adt.def.def_id(), tcx), args)
+}
+
+fn with_args(mut id: String, args: GenericArgsRef) -> String {
+    if !args.is_empty() {
+        let mut hasher = StableHasher::new();
+        with_no_trimmed_paths!(args.print_as_list()).hash(&mut hasher);
+        let hash = hasher.finish::<Fingerprint>().to_smaller_hash();
+        id.push_str(&format!("-{:016x}", hash.as_u64()));
+    }
+    id
+}
@@ -0,0 +1,188 @@
+//! Nodes touched by a patch, reported by `cargo upg impact`.
+//!
+//! A node is touched if its span overlaps lines changed by a unified diff.
+//! Public safe functions reaching touched nodes through calls or adt accesses
+//! are reported too, since their safety may rely on what the patch changed,
+//! even if the patch has no `unsafe` in it.
+
+use crate::{
+    graph::Graph,
+    model::Span,
+    query::NodeRef,
+    utils::{FxHashMap, FxIndexMap, FxIndexSet},
+};
+use serde::Serialize;
+use std::{collections::VecDeque, fmt::Write, path::Path};
+
+/// Lines changed in the new version of each file, keyed by paths in the patch
+/// without the `b/` prefix. Removed lines count as the line following them,
+/// so a removal inside a function touches the function. Deleted files are
+/// skipped, since nodes can't be in them.
+pub fn changed_lines(patch: &str) -> FxIndexMap<String, Vec<u32>> {
+    let mut files = FxIndexMap::<String, Vec<u32>>::default();
+    let mut file = None;
+    // The next line in the new file, and lines left in the old and new hunk.
+    let (mut line, mut old_left, mut new_left) = (0, 0, 0);
+    for text in patch.lines() {
+        if old_left == 0 && new_left == 0 {
+            if let Some(path) = text.strip_prefix("+++ ") {
+                // Timestamps may follow the path after a tab.
+                let path = path.split('\t').next().unwrap_or_default().trim_end();
+                file = (path != "/dev/null")
+                    .then(|| path.strip_prefix("b/").unwrap_or(path).to_owned());
+            } else if let Some(hunk) = text.strip_prefix("@@ ") {
+                // `@@ -l,s +l,s @@`, where a size of 1 may be omitted.
+                let mut ranges = hunk.split(' ').take(2).map(|range| {
+                    let range = range.get(1..).unwrap_or_default();
+                    let mut nums = range.split(',').map(|n| n.parse().unwrap_or(0));
+                    (nums.next().unwrap_or(0), nums.next().unwrap_or(1))
+                });
+                let (old, new) = (ranges.next(), ranges.next());
+                (_, old_left) = old.unwrap_or_default();
+                (line, new_left) = new.unwrap_or_default();
+            }
+            continue;
+        }
+        let Some(file) = &file else {
+            (old_left, new_left) = (0, 0);
+            continue;
+        };
+        let lines = files.entry(file.clone()).or_default();
+        match text.as_bytes().first() {
+            Some(b'+') => {
+                lines.push(line);
+                (line, new_left) = (line + 1, new_left.saturating_sub(1));
+            }
+            Some(b'-') => {
+                lines.push(line);
+                old_left = old_left.saturating_sub(1);
+            }
+            // No newline at end of file.
+            Some(b'\\') => (),
+            _ => {
+                line += 1;
+                old_left = old_left.saturating_sub(1);
+                new_left = new_left.saturating_sub(1);
+            }
+        }
+    }
+    for lines in files.values_mut() {
+        lines.dedup();
+    }
+    files.retain(|_, lines| !lines.is_empty());
+    files
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Impact {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub touched: Vec<Touched>,
+    pub reached_from: Vec<Reacher>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Touched {
+    pub node: NodeRef,
+    /// `function` or `adt`.
+    pub kind: &'static str,
+    /// None for adts.
+    pub safe: Option<bool>,
+    pub span: String,
+}
+
+/// A public safe function and the touched nodes it reaches, excluding itself.
+#[derive(Clone, Debug, Serialize)]
+pub struct Reacher {
+    pub node: NodeRef,
+    pub reaches: Vec<NodeRef>,
+}
+
+impl Impact {
+    pub fn new(graph: &Graph, changed: &FxIndexMap<String, Vec<u32>>) -> Impact {
+        let mut impact = Impact {
+            krate: graph.krate.clone(),
+            ..Default::default()
+        };
+        let touched: FxIndexSet<&str> = graph
+            .nodes()
+            .filter(|node| Span::parse(node.span()).is_some_and(|span| overlaps(&span, changed)))
+            .map(|node| {
+                impact.touched.push(Touched {
+                    node: NodeRef::new(graph, node.id()),
+                    kind: node.kind(),
+                    safe: node.safe(),
+                    span: node.span().to_owned(),
+                });
+                node.id()
+            })
+            .collect();
+
+        let mut callers = FxHashMap::<&str, Vec<&str>>::default();
+        for edge in graph.edges() {
+            callers.entry(edge.target).or_default().push(edge.source);
+        }
+        // Touched nodes reached by each node, found backwards from each of them.
+        let mut reaches = FxIndexMap::<&str, FxIndexSet<&str>>::default();
+        for &target in &touched {
+            let mut visited = FxIndexSet::default();
+            let mut queue = VecDeque::from([target]);
+            while let Some(id) = queue.pop_front() {
+                for &caller in callers.get(id).into_iter().flatten() {
+                    if caller != target && visited.insert(caller) {
+                        reaches.entry(caller).or_default().insert(target);
+                        queue.push_back(caller);
+                    }
+                }
+            }
+        }
+        for f in graph.functions.iter().filter(|f| f.public && f.safe) {
+            if let Some(targets) = reaches.get(&*f.id) {
+                impact.reached_from.push(Reacher {
+                    node: NodeRef::new(graph, &f.id),
+                    reaches: targets.iter().map(|id| NodeRef::new(graph, id)).collect(),
+                });
+            }
+        }
+        impact
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.touched.is_empty()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut buf = String::new();
+        if self.is_empty() {
+            return buf;
+        }
+        _ = writeln!(buf, "crate {}", self.krate);
+        for t in &self.touched {
+            let safety = match t.safe {
+                Some(true) => "safe ",
+                Some(false) => "unsafe ",
+                None => "",
+            };
+            _ = writeln!(buf, "  {safety}{} {} {}", t.kind, t.node.name, t.span);
+        }
+        for r in &self.reached_from {
+            let names: Vec<_> = r.reaches.iter().map(|node| &*node.name).collect();
+            _ = writeln!(buf, "  pub fn {} reaches {}", r.node.name, names.join(", "));
+        }
+        buf
+    }
+}
+
+/// Paths in spans may be absolute or relative to the workspace root, and
+/// paths in patches are relative to the repository root, so either one may
+/// be a suffix of the other.
+fn overlaps(span: &Span, changed: &FxIndexMap<String, Vec<u32>>) -> bool {
+    let span_file = Path::new(&span.file);
+    changed.iter().any(|(file, lines)| {
+        let file = Path::new(file);
+        (span_file.ends_with(file) || file.ends_with(span_file))
+            && lines
+                .iter()
+                .any(|line| (span.line_lo..=span.line_hi).contains(line))
+    })
+}
@@ -75,23 +75,42 @@ impl AdtInfo {
                 }
             };
             match access {
-                AdtAccess::Ref => push(&mut self.as_argument.read, &mut self.otherwise.read),
-                AdtAccess::MutRef | AdtAccess::Deref => {
+                AdtAccess::Ref | AdtAccess::Deref => {
+                    push(&mut self.as_argument.read, &mut self.otherwise.read)
+                }
+                AdtAccess::MutRef | AdtAccess::MutDeref => {
                     push(&mut self.as_argument.write, &mut self.otherwise.write)
                 }
                 AdtAccess::Plain | AdtAccess::Unknown(_) => {
                     push(&mut self.as_argument.other, &mut self.otherwise.other)
                 }
-                AdtAccess::RefVariantField(idx) => {
+                AdtAccess::RefVariantField(idx) | AdtAccess::DerefVariantField(idx) => {
                     if let Some(idx) = idx.as_field_idx() {
-                        self.fields[idx].read = v_fn.iter().map(|f| f.fn_def).collect();
+                        self.fields[idx].read.extend(v_fn.iter().map(|f| f.fn_def));
                     }
                 }
-                AdtAccess::MutRefVariantField(idx) | AdtAccess::DerefVariantField(idx) => {
+                AdtAccess::MutRefVariantField(idx) | AdtAccess::MutDerefVariantField(idx) => {
                     if let Some(idx) = idx.as_field_idx() {
                         self.fields[idx].write.extend(v_fn.iter().map(|f| f.fn_def));
                     }
                 }
+                AdtAccess::ReadUnionField(idx) => {
+                    if let Some(idx) = idx.as_field_idx() {
+                        self.fields[idx].read.extend(v_fn.iter().map(|f| f.fn_def));
+                    }
+                }
+                AdtAccess::WriteUnionField(idx) => {
+                    if let Some(idx) = idx.as_field_idx() {
+                        self.fields[idx].write.extend(v_fn.iter().map(|f| f.fn_def));
+                    }
+                }
+                AdtAccess::ReinterpretUnionField(idx) => {
+                    if let Some(idx) = idx.as_field_idx() {
+                        self.fields[idx]
+                            .reinterpret
+                            .extend(v_fn.iter().map(|f| f.fn_def));
+                    }
+                }
             }
         }
 
@@ -108,11 +127,15 @@ pub struct FnDefAdt {
 /// Access a place w.r.t the adt or field.
 #[derive(Clone, Debug, Default)]
 pub struct Access {
-    /// Functions that only read the place via Ref or RefField.
+    /// Functions that only read the place via Ref, Deref, or their field kinds.
     /// FIXME: Interior mutability is not handled yet.
     pub read: ThinVec<FnDef>,
-    /// Functions that can write the place via MutRef, Deref, MutRefField, or DerefVariant.
+    /// Functions that can write the place via MutRef, MutDeref, or their field
+    /// kinds.
     pub write: ThinVec<FnDef>,
     /// Functions that in other ways access the place, like Plain or Unknown.
     pub other: ThinVec<FnDef>,
+    /// Functions that read a union field other than the one last written.
+    /// Always empty for non-union places.
+    pub reinterpret: ThinVec<FnDef>,
 }
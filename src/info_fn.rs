@@ -1,9 +1,9 @@
 use crate::adt::{Adt, AdtAccess, CacheAdt, LocalsAccess, VaraintFieldIdx, new_adt};
-use crate::analyze_fn_def::Collector;
-use crate::utils::{FxIndexMap, FxIndexSet, SmallVec};
+use crate::analyze_fn_def::{Assign, Collector};
+use crate::utils::{FxHashMap, FxIndexMap, FxIndexSet, SmallVec, ThinVec};
 use rustc_public::{
     mir::{Body, Mutability, ProjectionElem, mono::Instance},
-    ty::{GenericArgKind, RigidTy, Ty, TyKind},
+    ty::{AdtDef, AdtKind, GenericArgKind, GenericArgs, RigidTy, Span, Ty, TyKind},
 };
 
 pub struct FnInfo {
@@ -18,13 +18,35 @@ pub struct FnInfo {
     /// The number of arguments this function takes.
     pub arg_count: usize,
     /// All types and places mentioned in the function.
-    #[expect(unused)]
     pub collector: Collector,
     /// Direct callees in the function. The order is decided by MirVisitor,
     /// and called functions is monomorphized.
     pub callees: FxIndexSet<Instance>,
+    /// Direct calls with spans in the order of terminators. Calls through
+    /// function pointers or trait objects are not included.
+    pub calls: ThinVec<CallSite>,
     /// Direct adt places in the function. The adt is monomorphized.
     pub adts: FxIndexMap<Adt, LocalsAccess>,
+    /// Unsafe operations in the function. The order is decided by MirVisitor.
+    pub unsafe_ops: ThinVec<UnsafeOp>,
+}
+
+pub struct CallSite {
+    pub callee: Instance,
+    pub span: Span,
+}
+
+pub enum UnsafeOp {
+    /// Read a union field. `reinterpret` is true if another field of the same
+    /// local was written before in the function. Writes are tracked in the
+    /// order of basic blocks rather than by control flow, so a write on another
+    /// branch counts too.
+    UnionFieldRead {
+        adt: Adt,
+        field: VaraintFieldIdx,
+        reinterpret: bool,
+        span: Span,
+    },
 }
 
 impl FnInfo {
@@ -38,14 +60,94 @@ impl FnInfo {
             }
         }
 
-        let mut adts = FxIndexMap::default();
-        for place in &collector.v_place {
+        let mut calls = ThinVec::new();
+        for call in &collector.v_call {
+            if let Ok(ty) = call.func.ty(body.locals())
+                && let TyKind::RigidTy(RigidTy::FnDef(fn_def, args)) = ty.kind()
+                && let Ok(callee) = Instance::resolve(fn_def, &args)
+            {
+                calls.push(CallSite {
+                    callee,
+                    span: call.span,
+                });
+            }
+        }
+
+        let mut adts = FxIndexMap::<Adt, LocalsAccess>::default();
+        let mut unsafe_ops = ThinVec::new();
+        // The union field last written to for each local.
+        let mut union_written = FxHashMap::<usize, usize>::default();
+        // Assignments keyed by the assigned places.
+        let assigns: FxHashMap<usize, &Assign> = collector
+            .v_assign
+            .iter()
+            .map(|assign| (assign.places.start, assign))
+            .collect();
+        // The assigned place is visited before the rvalue, but it's written
+        // after the rvalue is evaluated, so `u.a = u.b` reads `b` first. The
+        // write is pending until the places of the rvalue are visited.
+        let mut pending_write = None;
+        for (idx, place) in collector.v_place.iter().enumerate() {
+            if let Some((end, local_idx, field_idx)) = pending_write
+                && idx >= end
+            {
+                union_written.insert(local_idx, field_idx);
+                pending_write = None;
+            }
             let local_idx = place.place.local;
-            if let Some(local_decl) = body.local_decl(local_idx) {
+            let Some(local_decl) = body.local_decl(local_idx) else {
+                continue;
+            };
+            // Initializing a union like `U { a }` writes the field.
+            if let Some(Assign {
+                places,
+                union_init: Some((def, args, field_idx)),
+            }) = assigns.get(&idx).copied()
+                && matches!(place.place.projection[..], [] | [ProjectionElem::Deref])
+            {
+                let adt = new_adt(*def, args.clone(), cache);
+                let local = adts.entry(adt).or_default();
+                local.locals.push(local_idx);
+                let field = VaraintFieldIdx::new_field(*field_idx);
+                local.access.insert(AdtAccess::WriteUnionField(field));
+                pending_write = Some((places.end, local_idx, *field_idx));
+            }
+            if let Some((def, args, field_idx)) =
+                union_field(&local_decl.ty, &place.place.projection)
+            {
+                let adt = new_adt(def, args, cache);
+                let field = VaraintFieldIdx::new_field(field_idx);
+                let local = adts.entry(adt.clone()).or_default();
+                local.locals.push(local_idx);
+                if place.mutating {
+                    match assigns.get(&idx) {
+                        Some(assign) => {
+                            pending_write = Some((assign.places.end, local_idx, field_idx));
+                        }
+                        None => _ = union_written.insert(local_idx, field_idx),
+                    }
+                    local.access.insert(AdtAccess::WriteUnionField(field));
+                } else {
+                    let reinterpret = union_written
+                        .get(&local_idx)
+                        .is_some_and(|&written| written != field_idx);
+                    local.access.insert(AdtAccess::ReadUnionField(field));
+                    if reinterpret {
+                        local.access.insert(AdtAccess::ReinterpretUnionField(field));
+                    }
+                    unsafe_ops.push(UnsafeOp::UnionFieldRead {
+                        adt,
+                        field,
+                        reinterpret,
+                        span: place.span,
+                    });
+                }
+            } else {
                 push_adt(
                     local_idx,
                     &local_decl.ty,
                     &place.place.projection,
+                    place.mutating,
                     &mut adts,
                     cache,
                 );
@@ -62,16 +164,40 @@ impl FnInfo {
             arg_count: body.arg_locals().len(),
             collector,
             callees,
+            calls,
             adts,
+            unsafe_ops,
         }
     }
 }
 
-/// Add an adt access or adt variant access.
+/// Returns the union and field index if the place projects into a union field,
+/// i.e. `u.a` for `u: U`, or `(*u).a` for a reference or raw pointer to `U`.
+fn union_field(ty: &Ty, proj: &[ProjectionElem]) -> Option<(AdtDef, GenericArgs, usize)> {
+    let TyKind::RigidTy(ty) = ty.kind() else {
+        return None;
+    };
+    let (ty, field_idx) = match (ty, proj) {
+        (ty @ RigidTy::Adt(..), [ProjectionElem::Field(idx, _), ..]) => (ty, *idx),
+        (
+            RigidTy::Ref(_, ty, _) | RigidTy::RawPtr(ty, _),
+            [ProjectionElem::Deref, ProjectionElem::Field(idx, _), ..],
+        ) => (ty.kind().rigid()?.clone(), *idx),
+        _ => return None,
+    };
+    match ty {
+        RigidTy::Adt(def, args) if def.kind() == AdtKind::Union => Some((def, args, field_idx)),
+        _ => None,
+    }
+}
+
+/// Add an adt access or adt variant access. `mutating` is whether the place
+/// is written or mutably borrowed.
 fn push_adt(
     idx: usize,
     ty: &Ty,
     proj: &[ProjectionElem],
+    mutating: bool,
     adts: &mut FxIndexMap<Adt, LocalsAccess>,
     cache: &mut CacheAdt,
 ) {
@@ -86,10 +212,21 @@ fn push_adt(
             local.locals.push(idx);
             // FIXME: ProjectionElem::Downcast(VariantIdx) should also be handled.
             match proj {
-                [ProjectionElem::Deref, ProjectionElem::Field(idx, _), ..] => {
-                    let value = AdtAccess::DerefVariantField(VaraintFieldIdx::new_field(*idx));
+                [
+                    ProjectionElem::Deref,
+                    ProjectionElem::Field(idx, _),
+                    rest @ ..,
+                ] => {
+                    let field_idx = VaraintFieldIdx::new_field(*idx);
+                    // Writing through a pointer in the field doesn't write it.
+                    let value = if mutating && !rest.contains(&ProjectionElem::Deref) {
+                        AdtAccess::MutDerefVariantField(field_idx)
+                    } else {
+                        AdtAccess::DerefVariantField(field_idx)
+                    };
                     local.access.insert(value)
                 }
+                [ProjectionElem::Deref] if mutating => local.access.insert(AdtAccess::MutDeref),
                 [ProjectionElem::Deref] => local.access.insert(AdtAccess::Deref),
                 [] => local.access.insert(AdtAccess::Plain),
                 _ => local.access.insert(AdtAccess::Unknown(proj.into())),
@@ -120,11 +257,13 @@ fn push_adt(
                     };
                     local.access.insert(acc);
                 }
-                _ => push_adt(idx, &ref_ty, proj, adts, cache),
+                _ => push_adt(idx, &ref_ty, proj, mutating, adts, cache),
             }
         }
-        RigidTy::Tuple(v) => v.iter().for_each(|ty| push_adt(idx, ty, proj, adts, cache)),
-        RigidTy::Slice(ty) => push_adt(idx, &ty, proj, adts, cache),
+        RigidTy::Tuple(v) => v
+            .iter()
+            .for_each(|ty| push_adt(idx, ty, proj, mutating, adts, cache)),
+        RigidTy::Slice(ty) => push_adt(idx, &ty, proj, mutating, adts, cache),
         _ => (),
     }
 }
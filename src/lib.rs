@@ -0,0 +1,79 @@
+//! The unsafety propagation graph.
+//!
+//! The serialized model in [`model`] and [`graph`], the loaders of output
+//! directories in [`load`], and the renderers don't depend on rustc. Tools only
+//! reading results can depend on the crate with `default-features = false`.
+//!
+//! The analysis needs `rustc_private` and is behind the default `driver`
+//! feature. [`analyze`] has the entry points, [`info_fn::FnInfo`] and
+//! [`info_adt::AdtInfo`] are the per-function and per-adt results, and
+//! [`output`] turns them into the model. Custom analyses implement
+//! [`pass::Pass`] and run in a driver calling [`driver::main`].
+
+#![cfg_attr(feature = "driver", feature(rustc_private))]
+
+#[cfg(feature = "driver")]
+extern crate rustc_abi;
+#[cfg(feature = "driver")]
+extern crate rustc_data_structures;
+#[cfg(feature = "driver")]
+extern crate rustc_driver;
+#[cfg(feature = "driver")]
+extern crate rustc_hir;
+#[cfg(feature = "driver")]
+extern crate rustc_interface;
+#[cfg(feature = "driver")]
+extern crate rustc_lint;
+#[cfg(feature = "driver")]
+extern crate rustc_lint_defs;
+#[cfg(feature = "driver")]
+extern crate rustc_middle;
+#[cfg(feature = "driver")]
+extern crate rustc_public;
+#[cfg(feature = "driver")]
+extern crate rustc_public_bridge;
+#[cfg(feature = "driver")]
+extern crate rustc_session;
+#[cfg(feature = "driver")]
+extern crate rustc_span;
+
+pub mod audit;
+pub mod config;
+pub mod datalog;
+pub mod diff;
+pub mod dot;
+pub mod graph;
+pub mod graphml;
+pub mod impact;
+pub mod load;
+pub mod model;
+pub mod policy;
+pub mod query;
+pub mod schema;
+pub mod sqlite;
+pub mod utils;
+
+#[cfg(feature = "driver")]
+pub mod adt;
+#[cfg(feature = "driver")]
+pub mod analyze;
+#[cfg(feature = "driver")]
+pub mod analyze_fn_def;
+#[cfg(feature = "driver")]
+pub mod driver;
+#[cfg(feature = "driver")]
+pub mod id;
+#[cfg(feature = "driver")]
+pub mod info_adt;
+#[cfg(feature = "driver")]
+pub mod info_fn;
+#[cfg(feature = "driver")]
+pub mod lint;
+#[cfg(feature = "driver")]
+pub mod output;
+#[cfg(feature = "driver")]
+pub mod pass;
+#[cfg(feature = "driver")]
+pub mod sink;
+#[cfg(feature = "driver")]
+pub mod summary;
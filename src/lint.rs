@@ -0,0 +1,219 @@
+//! Findings reported as compiler warnings in the `upg` tool namespace.
+//!
+//! With `--upg-lints`, the driver registers the `upg` tool and sets
+//! `--cfg upg`, so lints can be controlled like clippy's, while plain rustc
+//! ignores them:
+//!
+//! ```ignore
+//! #[cfg_attr(upg, allow(upg::unnecessary_unsafe_fn))]
+//! pub unsafe fn set_len(&mut self, len: usize) { ... }
+//! ```
+//!
+//! Lints are checked by [`Lints`], a pass run by the driver, and are attached
+//! to the function they're found in, so `allow` works on the function or any
+//! item containing it.
+
+use crate::{
+    graph::Graph,
+    id,
+    info_fn::FnInfo,
+    model::safety_doc,
+    output,
+    pass::Pass,
+    summary::is_safe,
+    utils::{FxHashMap, FxIndexMap},
+};
+use rustc_hir::def::DefKind;
+use rustc_lint_defs::{Lint, declare_tool_lint};
+use rustc_middle::ty::TyCtxt;
+use rustc_public::{
+    CrateDef,
+    mir::{Body, ProjectionElem, TerminatorKind, mono::Instance},
+    rustc_internal::internal,
+    ty::FnDef,
+};
+use rustc_span::{Span, def_id::LocalDefId};
+use serde_json::Value;
+
+declare_tool_lint! {
+    /// A call to an unsafe function of the crate without a `# Safety` section
+    /// in its doc, so callers can't know what they must uphold.
+    pub upg::UNDOCUMENTED_UNSAFE_CALL,
+    Warn,
+    "calls to unsafe functions without a `# Safety` doc"
+}
+
+declare_tool_lint! {
+    /// A safe function that may write a field unsafe functions access, so
+    /// safe code may break what unsafe code relies on. Writes are judged like
+    /// [`AdtAccess::is_write`](crate::model::AdtAccess::is_write).
+    pub upg::SAFE_CRITICAL_FIELD_WRITE,
+    Warn,
+    "safe functions that may write fields unsafe functions access"
+}
+
+declare_tool_lint! {
+    /// An unsafe function doing no unsafe operations and touching no adts,
+    /// so it has no obvious contract to uphold.
+    pub upg::UNNECESSARY_UNSAFE_FN,
+    Warn,
+    "unsafe functions without unsafe operations"
+}
+
+pub static LINTS: &[&Lint] = &[
+    UNDOCUMENTED_UNSAFE_CALL,
+    SAFE_CRITICAL_FIELD_WRITE,
+    UNNECESSARY_UNSAFE_FN,
+];
+
+/// Per-function facts gathered while analyzing, and linted with the graph.
+#[derive(Default)]
+pub struct Lints {
+    /// Local functions keyed by node IDs.
+    functions: FxIndexMap<String, FnFacts>,
+}
+
+struct FnFacts {
+    did: LocalDefId,
+    /// Calls to unsafe functions without `# Safety` docs, with callee names.
+    undocumented_calls: Vec<(Span, String)>,
+    /// Unsafe operations or calls to unsafe functions.
+    does_unsafe: bool,
+}
+
+impl Pass for Lints {
+    fn name(&self) -> &str {
+        "lints"
+    }
+
+    fn function(
+        &mut self,
+        fn_def: FnDef,
+        body: &Body,
+        info: &FnInfo,
+        tcx: TyCtxt,
+    ) -> Option<Value> {
+        let did = internal(tcx, fn_def.def_id()).as_local()?;
+        let mut facts = FnFacts {
+            did,
+            undocumented_calls: Vec::new(),
+            does_unsafe: !info.unsafe_ops.is_empty() || unsafe_body(body, info),
+        };
+        facts.does_unsafe |= info.callees.iter().any(|callee| is_unsafe_fn(callee, tcx));
+        // Docs of other crates are their authors' business, and often refer
+        // to other items instead of having a `# Safety` section.
+        for call in &info.calls {
+            if !call.callee.def.krate().is_local || !is_unsafe_fn(&call.callee, tcx) {
+                continue;
+            }
+            let doc = output::doc_string(call.callee.def.def_id(), &Default::default(), tcx);
+            if safety_doc(&doc).is_none() {
+                let span = internal(tcx, call.span).source_callsite();
+                facts.undocumented_calls.push((span, call.callee.name()));
+            }
+        }
+        self.functions
+            .insert(id::def_hash(fn_def.def_id(), tcx), facts);
+        None
+    }
+
+    fn finish(&mut self, graph: &Graph, tcx: TyCtxt) -> FxIndexMap<String, Value> {
+        let facts = &self.functions;
+        let hir_id = |facts: &FnFacts| tcx.local_def_id_to_hir_id(facts.did);
+
+        for f in &graph.functions {
+            let Some(facts) = facts.get(&f.id) else {
+                continue;
+            };
+            for (span, callee) in &facts.undocumented_calls {
+                tcx.node_span_lint(UNDOCUMENTED_UNSAFE_CALL, hir_id(facts), *span, |diag| {
+                    diag.primary_message(format!(
+                        "call to unsafe fn `{callee}` without a `# Safety` doc"
+                    ));
+                });
+            }
+        }
+
+        let critical = graph.critical_fields();
+        let names: FxHashMap<&str, &str> = graph.adts.iter().map(|a| (&*a.id, &*a.name)).collect();
+        let functions: FxHashMap<&str, _> = graph.functions.iter().map(|f| (&*f.id, f)).collect();
+        for (writer, adt, field) in graph.field_writers() {
+            let Some(facts) = facts.get(writer) else {
+                continue;
+            };
+            if functions[writer].safe && critical.contains(&(adt, field.clone())) {
+                let adt = names.get(adt).copied().unwrap_or(adt);
+                let span = tcx.def_span(facts.did);
+                tcx.node_span_lint(SAFE_CRITICAL_FIELD_WRITE, hir_id(facts), span, |diag| {
+                    diag.primary_message(format!(
+                        "safe fn `{}` may write `{adt}.{field}`, which unsafe fns access",
+                        functions[writer].name
+                    ));
+                });
+            }
+        }
+
+        for f in graph
+            .functions
+            .iter()
+            .filter(|f| !f.safe && f.adts.is_empty())
+        {
+            let Some(facts) = facts.get(&f.id) else {
+                continue;
+            };
+            // Unsafe trait methods are unsafe because of the trait.
+            let did = facts.did.to_def_id();
+            let trait_method =
+                tcx.trait_of_assoc(did).is_some() || tcx.trait_impl_of_assoc(did).is_some();
+            if !facts.does_unsafe && !trait_method && f.ffi.is_none() {
+                let span = tcx.def_span(facts.did);
+                tcx.node_span_lint(UNNECESSARY_UNSAFE_FN, hir_id(facts), span, |diag| {
+                    diag.primary_message(format!(
+                        "unsafe fn `{}` does no unsafe operations",
+                        f.name
+                    ));
+                });
+            }
+        }
+        FxIndexMap::default()
+    }
+
+    /// Lints are emitted instead, so nodes are still streamed.
+    fn finish_results(&self) -> bool {
+        false
+    }
+}
+
+/// Closures and shims are not unsafe by themselves.
+fn is_unsafe_fn(callee: &Instance, tcx: TyCtxt) -> bool {
+    let def_id = callee.def.def_id();
+    matches!(
+        tcx.def_kind(internal(tcx, def_id)),
+        DefKind::Fn | DefKind::AssocFn
+    ) && !is_safe(callee)
+}
+
+/// Dereferences of raw pointers, including accesses to `static mut`, and
+/// inline assembly.
+fn unsafe_body(body: &Body, info: &FnInfo) -> bool {
+    let asm = body
+        .blocks
+        .iter()
+        .any(|block| matches!(block.terminator.kind, TerminatorKind::InlineAsm { .. }));
+    asm || info.collector.v_place.iter().any(|place| {
+        let place = &place.place;
+        let Some(mut ty) = body.locals().get(place.local).map(|decl| decl.ty) else {
+            return false;
+        };
+        for elem in &place.projection {
+            if matches!(elem, ProjectionElem::Deref) && ty.kind().is_raw_ptr() {
+                return true;
+            }
+            match elem.ty(ty) {
+                Ok(next) => ty = next,
+                Err(_) => return false,
+            }
+        }
+        false
+    })
+}
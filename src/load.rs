@@ -0,0 +1,77 @@
+//! Loaders of output directories written in the JSON format.
+//!
+//! `index.json` lists the node files of a crate, so a crate directory is read
+//! without listing directories. Files in other schema versions are rejected.
+
+use crate::{
+    graph::Graph,
+    model::{Index, Names, SCHEMA_VERSION, Summary},
+    utils::with_path,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Load the graph of a crate from `dir`, i.e. `base/crate_name`. The summary is
+/// None if `summary.json` doesn't exist.
+pub fn load_crate(dir: &Path) -> io::Result<Graph> {
+    let index: Index = read_json(&dir.join("index.json"))?;
+    let mut graph = Graph::new(&index.krate);
+    for f in &index.functions {
+        graph.functions.push(read_json(&dir.join(&f.path))?);
+    }
+    for a in &index.adts {
+        graph.adts.push(read_json(&dir.join(&a.path))?);
+    }
+    for s in &index.stubs {
+        graph.stubs.push(read_json(&dir.join(&s.path))?);
+    }
+
+    let names: Names = read_json(&dir.join("names.json"))?;
+    graph.names = names.names;
+
+    let path = dir.join("summary.json");
+    if path.exists() {
+        let summary: Summary = read_json(&path)?;
+        graph.summary = Some(summary);
+    }
+    Ok(graph)
+}
+
+/// Load all crates under the base directory, sorted by crate directories.
+/// Directories without `index.json` are skipped.
+pub fn load_all(base: &Path) -> io::Result<Vec<Graph>> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(base)
+        .map_err(with_path(base))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()
+        .map_err(with_path(base))?;
+    dirs.sort();
+    dirs.iter()
+        .filter(|dir| dir.join("index.json").is_file())
+        .map(|dir| load_crate(dir))
+        .collect()
+}
+
+/// Deserialize a JSON file of the model. Every file has `schema_version`.
+fn read_json<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    #[derive(serde::Deserialize)]
+    struct Version {
+        schema_version: u32,
+    }
+
+    let json = fs::read_to_string(path).map_err(with_path(path))?;
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let version: Version =
+        serde_json::from_str(&json).map_err(|err| invalid(format!("{}: {err}", path.display())))?;
+    if version.schema_version != SCHEMA_VERSION {
+        return Err(invalid(format!(
+            "{}: schema version {} is not supported, expected {SCHEMA_VERSION}",
+            path.display(),
+            version.schema_version
+        )));
+    }
+    serde_json::from_str(&json).map_err(|err| invalid(format!("{}: {err}", path.display())))
+}
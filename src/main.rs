@@ -1,57 +1,5 @@
 #![feature(rustc_private)]
 
-extern crate rustc_abi;
-extern crate rustc_data_structures;
-extern crate rustc_driver;
-extern crate rustc_hir;
-extern crate rustc_interface;
-extern crate rustc_middle;
-extern crate rustc_public;
-extern crate rustc_public_bridge;
-extern crate rustc_span;
-
-use rustc_middle::ty::TyCtxt;
-use std::ops::ControlFlow;
-
-mod adt;
-mod analyze_fn_def;
-mod info_adt;
-mod info_fn;
-mod output;
-
-mod utils;
-pub use utils::{FxIndexMap, FxIndexSet};
-
 fn main() {
-    let rustc_args: Vec<_> = std::env::args().collect();
-    _ = rustc_public::run_with_tcx!(&rustc_args, run);
-}
-
-fn run(tcx: TyCtxt) -> ControlFlow<(), ()> {
-    let local_crate = rustc_public::local_crate();
-    let fn_defs = local_crate.fn_defs();
-
-    let mut cache_adt = Default::default();
-    let writer = output::Writer::new(&local_crate.name);
-    let mut map_fn = FxIndexMap::with_capacity_and_hasher(fn_defs.len(), Default::default());
-
-    for fn_def in fn_defs {
-        if let Some(body) = fn_def.body() {
-            let collector = analyze_fn_def::collect(&body);
-            let finfo = info_fn::FnInfo::new(collector, &body, &mut cache_adt);
-
-            let out_func = output::Function::new(fn_def, &finfo, &body, tcx);
-            out_func.dump(&writer);
-
-            map_fn.insert(fn_def, finfo);
-        }
-    }
-
-    let map_adt = info_adt::adt_info(&map_fn);
-    for (adt, adt_info) in &map_adt {
-        let out_adt = output::Adt::new(adt, adt_info, tcx);
-        out_adt.dump(&writer);
-    }
-
-    ControlFlow::Break(())
+    unsafety_propagation_graph::driver::main(Vec::new());
 }
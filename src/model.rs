@@ -0,0 +1,496 @@
+//! The serialized graph model.
+//!
+//! These are the shapes of the JSON files in an output directory. They don't
+//! depend on rustc, so tools can deserialize them with
+//! `default-features = false`. The driver builds them in [`crate::output`].
+
+use crate::{
+    graph::Graph,
+    utils::{FxIndexMap, FxIndexSet},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the output format. Bump it on incompatible changes, i.e.
+/// fields removed, renamed or changed in type. New fields don't bump it, since
+/// readers ignore unknown fields.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Function {
+    pub schema_version: u32,
+    /// The stable node ID, which is also the file stem. See [`crate::id`].
+    pub id: String,
+    pub name: String,
+    /// The path of the module containing the function.
+    pub module: String,
+    pub safe: bool,
+    /// Reachable from other crates.
+    pub public: bool,
+    /// IDs of callees.
+    pub callees: Vec<String>,
+    /// Direct calls in the order of call terminators.
+    pub calls: Vec<CallSite>,
+    /// Accesses keyed by adt IDs.
+    pub adts: FxIndexMap<String, Vec<AdtAccess>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsafe_ops: Vec<UnsafeOp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ffi: Option<Ffi>,
+    pub span: String,
+    pub src: String,
+    pub mir: String,
+    pub doc: String,
+    /// Results of custom passes keyed by pass names. See [`crate::pass`].
+    #[serde(default, skip_serializing_if = "FxIndexMap::is_empty")]
+    pub passes: FxIndexMap<String, Value>,
+}
+
+impl Function {
+    /// Whether the callee is unsafe as recorded at its call sites. None if it
+    /// has no call site, e.g. a function pointer taken without a call.
+    pub fn unsafe_callee(&self, callee: &str) -> Option<bool> {
+        let call = self.calls.iter().find(|call| call.callee == callee)?;
+        Some(call.unsafe_callee)
+    }
+}
+
+/// The `# Safety` section of a doc string without the heading, or None if
+/// there is no such section. Lines in code blocks are not headings.
+pub fn safety_doc(doc: &str) -> Option<String> {
+    let mut section: Option<Vec<&str>> = None;
+    let mut in_code = false;
+    for line in doc.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+        } else if !in_code && trimmed.starts_with('#') {
+            if section.is_some() {
+                break;
+            }
+            if trimmed
+                .trim_start_matches('#')
+                .trim()
+                .eq_ignore_ascii_case("safety")
+            {
+                section = Some(Vec::new());
+                continue;
+            }
+        }
+        if let Some(lines) = &mut section {
+            lines.push(line);
+        }
+    }
+    Some(section?.join("\n").trim().to_owned())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CallSite {
+    /// The callee ID.
+    pub callee: String,
+    pub span: String,
+    /// The call is inside an `unsafe {}` block written in the function.
+    pub unsafe_block: bool,
+    /// The callee is an unsafe fn. Recorded at the call, because the callee
+    /// may not be emitted.
+    #[serde(default)]
+    pub unsafe_callee: bool,
+}
+
+/// The foreign function interface of a function.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Ffi {
+    pub kind: FfiKind,
+    /// The ABI string like `C` or `system`.
+    pub abi: String,
+    /// The symbol to link against or export as.
+    pub link_name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum FfiKind {
+    /// Declared in an `extern` block.
+    Import,
+    /// Exported via `#[no_mangle]` or `#[export_name]` with a non-Rust ABI,
+    /// i.e. an external entry point.
+    Export,
+}
+
+/// A lightweight node for a callee outside the local crate. The callee is not
+/// analyzed, but the graph is navigable one hop past the crate boundary.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Stub {
+    pub schema_version: u32,
+    pub id: String,
+    pub name: String,
+    pub module: String,
+    /// The key in the summary of the dependency crate, i.e. the def path hash.
+    pub key: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub safe: bool,
+    /// The monomorphized signature.
+    pub sig: String,
+    pub intrinsic: bool,
+    pub doc: String,
+    /// The summary from the dependency crate if it has been analyzed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<FnSummary>,
+    /// Results of custom passes keyed by pass names. See [`crate::pass`].
+    #[serde(default, skip_serializing_if = "FxIndexMap::is_empty")]
+    pub passes: FxIndexMap<String, Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind")]
+pub enum UnsafeOp {
+    UnionFieldRead {
+        /// The adt ID.
+        adt: String,
+        field: String,
+        reinterpret: bool,
+        span: String,
+    },
+}
+
+/// How a function accesses an adt or its field.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind")]
+pub enum AdtAccess {
+    Ref,
+    MutRef,
+    /// Read through a reference or raw pointer.
+    Deref,
+    /// Assign to or mutably borrow through a reference or raw pointer.
+    MutDeref,
+    Plain,
+    RefVariantField(VariantFieldRef),
+    MutRefVariantField(VariantFieldRef),
+    DerefVariantField(VariantFieldRef),
+    MutDerefVariantField(VariantFieldRef),
+    ReadUnionField(VariantFieldRef),
+    WriteUnionField(VariantFieldRef),
+    ReinterpretUnionField(VariantFieldRef),
+    /// Projections not recognized.
+    Unknown {
+        /// A compact projection path like `*.0@1[_3]`:
+        /// * `*` deref
+        /// * `.N` field
+        /// * `@N` downcast to variant
+        /// * `[_N]` index by a local, `[N]` and `[-N]` constant index from start and end
+        /// * `[A..B]` and `[A..-B]` subslice
+        /// * `@opaque` opaque cast
+        projection: String,
+    },
+}
+
+impl AdtAccess {
+    /// The tag in JSON.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AdtAccess::Ref => "Ref",
+            AdtAccess::MutRef => "MutRef",
+            AdtAccess::Deref => "Deref",
+            AdtAccess::MutDeref => "MutDeref",
+            AdtAccess::Plain => "Plain",
+            AdtAccess::RefVariantField(_) => "RefVariantField",
+            AdtAccess::MutRefVariantField(_) => "MutRefVariantField",
+            AdtAccess::DerefVariantField(_) => "DerefVariantField",
+            AdtAccess::MutDerefVariantField(_) => "MutDerefVariantField",
+            AdtAccess::ReadUnionField(_) => "ReadUnionField",
+            AdtAccess::WriteUnionField(_) => "WriteUnionField",
+            AdtAccess::ReinterpretUnionField(_) => "ReinterpretUnionField",
+            AdtAccess::Unknown { .. } => "Unknown",
+        }
+    }
+
+    pub fn variant_field(&self) -> Option<&VariantFieldRef> {
+        match self {
+            AdtAccess::RefVariantField(vf)
+            | AdtAccess::MutRefVariantField(vf)
+            | AdtAccess::DerefVariantField(vf)
+            | AdtAccess::MutDerefVariantField(vf)
+            | AdtAccess::ReadUnionField(vf)
+            | AdtAccess::WriteUnionField(vf)
+            | AdtAccess::ReinterpretUnionField(vf) => Some(vf),
+            _ => None,
+        }
+    }
+
+    /// The access may write the adt or the field, like `write` in [`Access`].
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            AdtAccess::MutRef
+                | AdtAccess::MutDeref
+                | AdtAccess::MutRefVariantField(_)
+                | AdtAccess::MutDerefVariantField(_)
+                | AdtAccess::WriteUnionField(_)
+        )
+    }
+
+    /// Reading a union field is unsafe.
+    pub fn is_unsafe(&self) -> bool {
+        matches!(
+            self,
+            AdtAccess::ReadUnionField(_) | AdtAccess::ReinterpretUnionField(_)
+        )
+    }
+}
+
+/// A variant or field with indices and resolved names. `variant` is null for
+/// struct or union fields, and `field` is null for enum variants.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct VariantFieldRef {
+    pub variant: Option<u32>,
+    pub field: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_name: Option<String>,
+}
+
+impl VariantFieldRef {
+    /// The resolved name like `field`, `Variant` or `Variant.field`.
+    pub fn name(&self) -> String {
+        match (&self.variant_name, &self.field_name) {
+            (Some(v), Some(f)) => format!("{v}.{f}"),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Adt {
+    pub schema_version: u32,
+    pub id: String,
+    pub name: String,
+    pub module: String,
+    /// Function IDs here and in accesses.
+    pub constructors: Vec<String>,
+    pub access_self_as_arg: Access,
+    pub access_self_as_locals: Access,
+    pub access_field: Vec<Access>,
+    pub span: String,
+    pub src: String,
+    pub kind: String,
+    pub doc_adt: String,
+    pub variant_fields: FxIndexMap<String, VariantField>,
+    /// Results of custom passes keyed by pass names. See [`crate::pass`].
+    #[serde(default, skip_serializing_if = "FxIndexMap::is_empty")]
+    pub passes: FxIndexMap<String, Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Access {
+    pub read: Vec<String>,
+    pub write: Vec<String>,
+    pub other: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reinterpret: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct VariantField {
+    pub variant: Option<u32>,
+    pub field: Option<u32>,
+    pub name: String,
+    pub doc: String,
+}
+
+/// The manifest of a crate written to `index.json`, so that front-ends and
+/// scripts can discover the data without listing directories.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Index {
+    pub schema_version: u32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub toolchain: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub counts: IndexCounts,
+    pub functions: Vec<IndexFunction>,
+    pub adts: Vec<IndexAdt>,
+    pub stubs: Vec<IndexStub>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct IndexCounts {
+    pub functions: usize,
+    pub unsafe_functions: usize,
+    pub adts: usize,
+    pub stubs: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct IndexFunction {
+    pub id: String,
+    pub name: String,
+    /// The file path relative to the crate directory.
+    pub path: String,
+    pub safe: bool,
+    pub span: String,
+    pub callees: usize,
+    pub adts: usize,
+    pub unsafe_ops: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct IndexAdt {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub kind: String,
+    pub span: String,
+    pub constructors: usize,
+    /// The number of functions accessing the adt or its fields.
+    pub accesses: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct IndexStub {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub safe: bool,
+}
+
+impl Index {
+    pub fn new(graph: &Graph, toolchain: &str) -> Index {
+        let mut index = Index {
+            schema_version: SCHEMA_VERSION,
+            krate: graph.krate.clone(),
+            toolchain: toolchain.to_owned(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            counts: IndexCounts::default(),
+            functions: Vec::new(),
+            adts: Vec::new(),
+            stubs: Vec::new(),
+        };
+        graph.functions.iter().for_each(|f| index.add_function(f));
+        graph.adts.iter().for_each(|a| index.add_adt(a));
+        graph.stubs.iter().for_each(|s| index.add_stub(s));
+        index
+    }
+
+    fn add_function(&mut self, func: &Function) {
+        self.counts.functions += 1;
+        self.counts.unsafe_functions += !func.safe as usize;
+        self.functions.push(IndexFunction {
+            id: func.id.clone(),
+            name: func.name.clone(),
+            path: format!("function/{}.json", func.id),
+            safe: func.safe,
+            span: func.span.clone(),
+            callees: func.callees.len(),
+            adts: func.adts.len(),
+            unsafe_ops: func.unsafe_ops.len(),
+        });
+    }
+
+    fn add_adt(&mut self, adt: &Adt) {
+        let accesses: FxIndexSet<&String> = [&adt.access_self_as_arg, &adt.access_self_as_locals]
+            .into_iter()
+            .chain(&adt.access_field)
+            .flat_map(|acc| [&acc.read, &acc.write, &acc.other, &acc.reinterpret])
+            .flatten()
+            .collect();
+        self.counts.adts += 1;
+        self.adts.push(IndexAdt {
+            id: adt.id.clone(),
+            name: adt.name.clone(),
+            path: format!("adt/{}.json", adt.id),
+            kind: adt.kind.clone(),
+            span: adt.span.clone(),
+            constructors: adt.constructors.len(),
+            accesses: accesses.len(),
+        });
+    }
+
+    fn add_stub(&mut self, stub: &Stub) {
+        self.counts.stubs += 1;
+        self.stubs.push(IndexStub {
+            id: stub.id.clone(),
+            name: stub.name.clone(),
+            path: format!("stub/{}.json", stub.id),
+            krate: stub.krate.clone(),
+            safe: stub.safe,
+        });
+    }
+}
+
+/// Display names of nodes and edge targets written to `names.json`.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Names {
+    pub schema_version: u32,
+    /// Names keyed by IDs.
+    pub names: FxIndexMap<String, String>,
+}
+
+impl Names {
+    pub fn new(names: FxIndexMap<String, String>) -> Names {
+        Names {
+            schema_version: SCHEMA_VERSION,
+            names,
+        }
+    }
+}
+
+/// A compact per-crate summary written to `dir/crate_name/summary.json`.
+///
+/// When a later crate is analyzed, the summaries of its dependencies are loaded
+/// to continue callee edges and transitive unsafety past the crate boundary.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Summary {
+    pub schema_version: u32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Functions with bodies, keyed by def path hashes.
+    pub functions: FxIndexMap<String, FnSummary>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FnSummary {
+    pub name: String,
+    pub safe: bool,
+    /// Keys of direct callees, which may live in other crates.
+    pub callees: Vec<String>,
+    /// The function is unsafe, or transitively calls an unsafe function.
+    pub reach_unsafe: bool,
+}
+
+/// A span parsed from strings like `path/to/file.rs:1:2: 3:4`, which is how
+/// `span` fields are serialized.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: String,
+    pub line_lo: u32,
+    pub col_lo: u32,
+    pub line_hi: u32,
+    pub col_hi: u32,
+}
+
+impl Span {
+    /// Returns None for empty or malformed spans.
+    pub fn parse(span: &str) -> Option<Span> {
+        let mut parts = span.rsplitn(5, ':').map(str::trim);
+        let mut num = || parts.next()?.parse().ok();
+        let (col_hi, line_hi, col_lo, line_lo) = (num()?, num()?, num()?, num()?);
+        let file = parts.next().filter(|file| !file.is_empty())?;
+        Some(Span {
+            file: file.to_owned(),
+            line_lo,
+            col_lo,
+            line_hi,
+            col_hi,
+        })
+    }
+}
@@ -1,91 +1,297 @@
+//! Constructors of the model types from rustc.
+
 use crate::{
-    adt::Adt as RawAdt,
+    adt::{Adt as RawAdt, AdtAccess as RawAdtAccess, VaraintFieldIdx},
+    config::Analysis,
+    id::{adt_id, callee_id, def_hash, instance_id},
     info_adt::{Access as RawAccess, AdtInfo},
-    info_fn::FnInfo,
-    utils::FxIndexMap,
+    info_fn::{FnInfo, UnsafeOp as RawUnsafeOp},
+    model::{
+        Access, Adt, AdtAccess, CallSite, Ffi, FfiKind, Function, SCHEMA_VERSION, Stub, UnsafeOp,
+        VariantField, VariantFieldRef,
+    },
+    summary::{Dependencies, is_safe},
+    utils::{FxIndexMap, FxIndexSet},
 };
 use rustc_middle::ty::TyCtxt;
 use rustc_public::{
     CrateDef, DefId,
-    mir::{Body, Safety},
+    mir::{Body, ProjectionElem, Safety, mono::Instance},
     rustc_internal::internal,
     ty::{FnDef, Span},
 };
-use rustc_span::def_id::DefId as IDefId;
-use serde::Serialize;
-use std::{fs, io, path::PathBuf};
-
-#[derive(Debug, Serialize)]
-pub struct Function {
-    pub name: String,
-    pub safe: bool,
-    pub callees: Vec<String>,
-    pub adts: FxIndexMap<String, Vec<String>>,
-    pub span: String,
-    pub src: String,
-    pub mir: String,
-    pub doc: String,
-}
+use rustc_public_bridge::IndexedVal;
+use rustc_span::def_id::{DefId as IDefId, LocalDefId};
 
 impl Function {
-    pub fn new(fn_def: FnDef, info: &FnInfo, body: &Body, tcx: TyCtxt) -> Self {
+    pub fn new(fn_def: FnDef, info: &FnInfo, body: &Body, opts: &Analysis, tcx: TyCtxt) -> Self {
         let name = fn_def.name();
-        let [span, src] = span_to_src(body.span, tcx);
-        let mir = {
+        let [span, src] = span_to_src(body.span, opts, tcx);
+        let mir = if !opts.mir {
+            String::new()
+        } else {
             let mut buf = Vec::with_capacity(1024);
             _ = body.dump(&mut buf, &name);
             String::from_utf8(buf).unwrap_or_default()
         };
+        // Instantiations of a local callee share the same ID.
+        let callees: FxIndexSet<_> = info
+            .callees
+            .iter()
+            .map(|instance| callee_id(instance, tcx))
+            .collect();
         Function {
+            schema_version: SCHEMA_VERSION,
+            id: def_hash(fn_def.def_id(), tcx),
             name,
+            module: module_path(fn_def.def_id(), tcx),
             safe: matches!(fn_def.fn_sig().value.safety, Safety::Safe),
-            callees: info
-                .callees
-                .iter()
-                .map(|instance| instance.name())
-                .collect(),
+            public: is_public(fn_def.def_id(), tcx),
+            callees: callees.into_iter().collect(),
+            calls: CallSite::new_all(fn_def, info, tcx),
             adts: info
                 .adts
                 .iter()
                 .map(|(adt, locals)| {
                     (
-                        adt.to_string(tcx),
-                        locals.access.iter().map(|acc| format!("{acc:?}")).collect(),
+                        adt_id(adt, tcx),
+                        locals
+                            .access
+                            .iter()
+                            .map(|acc| AdtAccess::new(acc, adt))
+                            .collect(),
                     )
                 })
                 .collect(),
+            unsafe_ops: info
+                .unsafe_ops
+                .iter()
+                .map(|op| UnsafeOp::new(op, tcx))
+                .collect(),
+            ffi: Ffi::new(fn_def, tcx),
             span,
             src,
             mir,
-            doc: doc_string(fn_def.def_id(), tcx),
+            doc: doc_string(fn_def.def_id(), opts, tcx),
+            passes: FxIndexMap::default(),
+        }
+    }
+
+    /// A function declared in an `extern` block. It has no body, so it's a leaf
+    /// node in the graph. Returns None if the function is not a foreign item.
+    pub fn new_foreign(fn_def: FnDef, opts: &Analysis, tcx: TyCtxt) -> Option<Self> {
+        if !tcx.is_foreign_item(internal(tcx, fn_def.def_id())) {
+            return None;
+        }
+        let [span, src] = span_to_src(fn_def.span(), opts, tcx);
+        Some(Function {
+            schema_version: SCHEMA_VERSION,
+            id: def_hash(fn_def.def_id(), tcx),
+            name: fn_def.name(),
+            module: module_path(fn_def.def_id(), tcx),
+            safe: matches!(fn_def.fn_sig().value.safety, Safety::Safe),
+            public: is_public(fn_def.def_id(), tcx),
+            callees: Vec::new(),
+            calls: Vec::new(),
+            adts: FxIndexMap::default(),
+            unsafe_ops: Vec::new(),
+            ffi: Ffi::new(fn_def, tcx),
+            span,
+            src,
+            mir: String::new(),
+            doc: doc_string(fn_def.def_id(), opts, tcx),
+            passes: FxIndexMap::default(),
+        })
+    }
+}
+
+impl CallSite {
+    fn new_all(fn_def: FnDef, info: &FnInfo, tcx: TyCtxt) -> Vec<CallSite> {
+        let blocks = internal(tcx, fn_def.def_id())
+            .as_local()
+            .map(|did| unsafe_blocks(did, tcx))
+            .unwrap_or_default();
+        info.calls
+            .iter()
+            .map(|call| {
+                let span = internal(tcx, call.span).source_callsite();
+                CallSite {
+                    callee: callee_id(&call.callee, tcx),
+                    span: call.span.diagnostic(),
+                    unsafe_block: blocks.iter().any(|block| block.contains(span)),
+                    unsafe_callee: !is_safe(&call.callee),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Spans of user-written `unsafe {}` blocks in the body, excluding closures.
+fn unsafe_blocks(did: LocalDefId, tcx: TyCtxt) -> Vec<rustc_span::Span> {
+    use rustc_hir::{
+        Block, BlockCheckMode, UnsafeSource,
+        intravisit::{Visitor, walk_block},
+    };
+
+    struct Collect(Vec<rustc_span::Span>);
+
+    impl<'v> Visitor<'v> for Collect {
+        fn visit_block(&mut self, block: &'v Block<'v>) {
+            if let BlockCheckMode::UnsafeBlock(UnsafeSource::UserProvided) = block.rules {
+                self.0.push(block.span);
+            }
+            walk_block(self, block);
         }
     }
 
-    pub fn dump(&self, writer: &Writer) {
-        writer.dump_json("function", &self.name, self);
+    let mut collect = Collect(Vec::new());
+    if let Some(body) = tcx.hir_maybe_body_owned_by(did) {
+        collect.visit_body(body);
     }
+    collect.0
 }
 
-#[derive(Debug, Serialize)]
-pub struct Adt {
-    pub name: String,
-    pub constructors: Vec<String>,
-    pub access_self_as_arg: Access,
-    pub access_self_as_locals: Access,
-    pub access_field: Vec<Access>,
-    pub span: String,
-    pub src: String,
-    pub kind: String,
-    pub doc_adt: String,
-    pub variant_fields: FxIndexMap<String, VariantField>,
+impl Ffi {
+    fn new(fn_def: FnDef, tcx: TyCtxt) -> Option<Ffi> {
+        let did = internal(tcx, fn_def.def_id());
+        let attrs = tcx.codegen_fn_attrs(did);
+        let abi = tcx.fn_sig(did).skip_binder().abi();
+        let kind = if tcx.is_foreign_item(did) {
+            FfiKind::Import
+        } else if attrs.contains_extern_indicator() && abi != rustc_abi::ExternAbi::Rust {
+            FfiKind::Export
+        } else {
+            return None;
+        };
+        let link_name = attrs.symbol_name.unwrap_or_else(|| tcx.item_name(did));
+        Some(Ffi {
+            kind,
+            abi: abi.as_str().to_owned(),
+            link_name: link_name.to_string(),
+        })
+    }
+}
+
+impl Stub {
+    pub fn new(instance: &Instance, deps: &mut Dependencies, opts: &Analysis, tcx: TyCtxt) -> Self {
+        let def_id = instance.def.def_id();
+        // Closures and shims have no signature by their def id.
+        let sig = instance.ty().kind().fn_sig().map(|sig| internal(tcx, sig));
+        let key = def_hash(def_id, tcx);
+        let krate = instance.def.krate().name;
+        Stub {
+            schema_version: SCHEMA_VERSION,
+            id: instance_id(instance, tcx),
+            name: instance.name(),
+            module: module_path(def_id, tcx),
+            summary: deps.get(&krate, &key).cloned(),
+            key,
+            krate,
+            safe: is_safe(instance),
+            sig: sig.map_or_else(String::new, |sig| {
+                rustc_middle::ty::print::with_no_trimmed_paths!(sig.to_string())
+            }),
+            intrinsic: instance.intrinsic_name().is_some(),
+            doc: doc_string(def_id, opts, tcx),
+            passes: FxIndexMap::default(),
+        }
+    }
+}
+
+impl UnsafeOp {
+    fn new(raw: &RawUnsafeOp, tcx: TyCtxt) -> UnsafeOp {
+        match raw {
+            RawUnsafeOp::UnionFieldRead {
+                adt,
+                field,
+                reinterpret,
+                span,
+            } => UnsafeOp::UnionFieldRead {
+                adt: adt_id(adt, tcx),
+                field: variant_field_name(adt, *field).unwrap_or_default(),
+                reinterpret: *reinterpret,
+                span: span.diagnostic(),
+            },
+        }
+    }
+}
+
+impl AdtAccess {
+    pub fn new(raw: &RawAdtAccess, adt: &RawAdt) -> AdtAccess {
+        let field = |idx: &VaraintFieldIdx| VariantFieldRef::new(*idx, adt);
+        match raw {
+            RawAdtAccess::Ref => AdtAccess::Ref,
+            RawAdtAccess::MutRef => AdtAccess::MutRef,
+            RawAdtAccess::Deref => AdtAccess::Deref,
+            RawAdtAccess::MutDeref => AdtAccess::MutDeref,
+            RawAdtAccess::Plain => AdtAccess::Plain,
+            RawAdtAccess::RefVariantField(idx) => AdtAccess::RefVariantField(field(idx)),
+            RawAdtAccess::MutRefVariantField(idx) => AdtAccess::MutRefVariantField(field(idx)),
+            RawAdtAccess::DerefVariantField(idx) => AdtAccess::DerefVariantField(field(idx)),
+            RawAdtAccess::MutDerefVariantField(idx) => AdtAccess::MutDerefVariantField(field(idx)),
+            RawAdtAccess::ReadUnionField(idx) => AdtAccess::ReadUnionField(field(idx)),
+            RawAdtAccess::WriteUnionField(idx) => AdtAccess::WriteUnionField(field(idx)),
+            RawAdtAccess::ReinterpretUnionField(idx) => {
+                AdtAccess::ReinterpretUnionField(field(idx))
+            }
+            RawAdtAccess::Unknown(proj) => AdtAccess::Unknown {
+                projection: projection_path(proj),
+            },
+        }
+    }
+}
+
+impl VariantFieldRef {
+    fn new(idx: VaraintFieldIdx, adt: &RawAdt) -> VariantFieldRef {
+        let variant_name = idx
+            .variant
+            .and_then(|v| variant_field_name(adt, VaraintFieldIdx::new_variant(v as usize)));
+        let field_name = idx.field.and_then(|_| variant_field_name(adt, idx));
+        VariantFieldRef {
+            variant: idx.variant,
+            field: idx.field,
+            variant_name,
+            field_name,
+        }
+    }
+}
+
+fn variant_field_name(adt: &RawAdt, idx: VaraintFieldIdx) -> Option<String> {
+    adt.variant_fields
+        .iter()
+        .find(|vf| vf.idx == idx)
+        .map(|vf| vf.name.to_string())
+}
+
+/// The projection path in [`AdtAccess::Unknown`].
+fn projection_path(proj: &[ProjectionElem]) -> String {
+    use std::fmt::Write;
+
+    let mut buf = String::new();
+    for elem in proj {
+        _ = match elem {
+            ProjectionElem::Deref => write!(buf, "*"),
+            ProjectionElem::Field(idx, _) => write!(buf, ".{idx}"),
+            ProjectionElem::Index(local) => write!(buf, "[_{local}]"),
+            ProjectionElem::ConstantIndex {
+                offset, from_end, ..
+            } => write!(buf, "[{}{offset}]", if *from_end { "-" } else { "" }),
+            ProjectionElem::Subslice { from, to, from_end } => {
+                write!(buf, "[{from}..{}{to}]", if *from_end { "-" } else { "" })
+            }
+            ProjectionElem::Downcast(idx) => write!(buf, "@{}", idx.to_index()),
+            ProjectionElem::OpaqueCast(_) => write!(buf, "@opaque"),
+        };
+    }
+    buf
 }
 
 impl Adt {
-    pub fn new(adt: &RawAdt, info: &AdtInfo, tcx: TyCtxt) -> Adt {
-        let [span, src] = span_to_src(adt.def.span(), tcx);
+    pub fn new(adt: &RawAdt, info: &AdtInfo, opts: &Analysis, tcx: TyCtxt) -> Adt {
+        let [span, src] = span_to_src(adt.def.span(), opts, tcx);
 
         let kind = format!("{:?}", adt.def.kind());
-        let doc_adt = doc_string(adt.def.def_id(), tcx);
+        let doc_adt = doc_string(adt.def.def_id(), opts, tcx);
 
         let mut variant_fields =
             FxIndexMap::with_capacity_and_hasher(adt.variant_fields.len(), Default::default());
@@ -93,14 +299,13 @@ impl Adt {
         for vf in &*adt.variant_fields {
             let idx = format!("{:?}", vf.idx);
             let name = vf.name.to_string();
-            let old = match (vf.idx.field, vf.idx.variant) {
+            let doc = match (vf.idx.field, vf.idx.variant) {
                 // unit struct: no fields
                 (None, None) => break,
                 // enum variant probably without fields
                 (None, Some(variant_idx)) => {
                     let did = adt_def.variant(variant_idx.into()).def_id;
-                    let doc = doc_string_internel_did(did, tcx);
-                    variant_fields.insert(idx, VariantField { name, doc })
+                    doc_string_internel_did(did, opts, tcx)
                 }
                 (Some(field_idx), None) => {
                     let variant = adt_def.variant(0u32.into());
@@ -108,66 +313,60 @@ impl Adt {
                         .fields
                         .get(rustc_abi::FieldIdx::from_u32(field_idx))
                         .unwrap();
-                    let doc = doc_string_internel_did(field.did, tcx);
-                    variant_fields.insert(idx, VariantField { name, doc })
-                }
-                (Some(_), Some(_)) => {
-                    let doc = String::new();
-                    variant_fields.insert(idx, VariantField { name, doc })
+                    doc_string_internel_did(field.did, opts, tcx)
                 }
+                (Some(_), Some(_)) => String::new(),
             };
+            let variant_field = VariantField {
+                variant: vf.idx.variant,
+                field: vf.idx.field,
+                name,
+                doc,
+            };
+            let old = variant_fields.insert(idx, variant_field);
             assert!(old.is_none(), "{adt_def:?}: {vf:?} has been inserted")
         }
 
         Adt {
+            schema_version: SCHEMA_VERSION,
+            id: adt_id(adt, tcx),
             name: adt.to_string(tcx),
-            constructors: v_fn_name(&info.constructors),
-            access_self_as_arg: Access::new(&info.as_argument),
-            access_self_as_locals: Access::new(&info.otherwise),
-            access_field: info.fields.iter().map(Access::new).collect(),
+            module: module_path(adt.def.def_id(), tcx),
+            constructors: v_fn_id(&info.constructors, tcx),
+            access_self_as_arg: Access::new(&info.as_argument, tcx),
+            access_self_as_locals: Access::new(&info.otherwise, tcx),
+            access_field: info.fields.iter().map(|f| Access::new(f, tcx)).collect(),
             span,
             src,
             kind,
             doc_adt,
             variant_fields,
+            passes: FxIndexMap::default(),
         }
     }
-
-    pub fn dump(&self, writer: &Writer) {
-        writer.dump_json("adt", &self.name, self);
-    }
-}
-
-#[derive(Debug, Serialize)]
-pub struct Access {
-    pub read: Vec<String>,
-    pub write: Vec<String>,
-    pub other: Vec<String>,
 }
 
 impl Access {
-    fn new(raw: &RawAccess) -> Access {
+    fn new(raw: &RawAccess, tcx: TyCtxt) -> Access {
         Access {
-            read: v_fn_name(&raw.read),
-            write: v_fn_name(&raw.write),
-            other: v_fn_name(&raw.other),
+            read: v_fn_id(&raw.read, tcx),
+            write: v_fn_id(&raw.write, tcx),
+            other: v_fn_id(&raw.other, tcx),
+            reinterpret: v_fn_id(&raw.reinterpret, tcx),
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct VariantField {
-    pub name: String,
-    pub doc: String,
-}
-
-fn v_fn_name(v: &[FnDef]) -> Vec<String> {
-    v.iter().map(|c| c.name()).collect()
+fn v_fn_id(v: &[FnDef], tcx: TyCtxt) -> Vec<String> {
+    v.iter().map(|c| def_hash(c.def_id(), tcx)).collect()
 }
 
 /// Span to string and source code.
-fn span_to_src(span: Span, tcx: TyCtxt) -> [String; 2] {
+fn span_to_src(span: Span, opts: &Analysis, tcx: TyCtxt) -> [String; 2] {
     let span_str = span.diagnostic();
+    if !opts.src {
+        return [span_str, String::new()];
+    }
 
     let span = internal(tcx, span);
     let src_map = tcx.sess.source_map();
@@ -177,17 +376,48 @@ fn span_to_src(span: Span, tcx: TyCtxt) -> [String; 2] {
     [span_str, src]
 }
 
-fn doc_string(def_id: DefId, tcx: TyCtxt) -> String {
+pub fn is_public(def_id: DefId, tcx: TyCtxt) -> bool {
     let did = internal(tcx, def_id);
-    doc_string_internel_did(did, tcx)
+    did.as_local()
+        .is_some_and(|did| tcx.effective_visibilities(()).is_exported(did))
+}
+
+/// The path of the closest module containing the definition, e.g. `core::ptr`.
+/// The crate root is the crate name.
+fn module_path(def_id: DefId, tcx: TyCtxt) -> String {
+    let mut did = internal(tcx, def_id);
+    while let Some(parent) = tcx.opt_parent(did) {
+        did = parent;
+        if let rustc_hir::def::DefKind::Mod = tcx.def_kind(did) {
+            break;
+        }
+    }
+    if did.is_crate_root() {
+        return tcx.crate_name(did.krate).to_string();
+    }
+    let path = rustc_middle::ty::print::with_no_trimmed_paths!(tcx.def_path_str(did));
+    if did.is_local() {
+        // Local paths are printed without the crate name.
+        format!("{}::{path}", tcx.crate_name(did.krate))
+    } else {
+        path
+    }
 }
 
-fn doc_string_internel_did(did: IDefId, tcx: TyCtxt) -> String {
+pub(crate) fn doc_string(def_id: DefId, opts: &Analysis, tcx: TyCtxt) -> String {
+    let did = internal(tcx, def_id);
+    doc_string_internel_did(did, opts, tcx)
+}
+
+fn doc_string_internel_did(did: IDefId, opts: &Analysis, tcx: TyCtxt) -> String {
     use rustc_hir::Attribute;
     use rustc_hir::attrs::AttributeKind;
     use std::fmt::Write;
 
     let mut buf = String::new();
+    if !opts.doc {
+        return buf;
+    }
     for attr in tcx.get_all_attrs(did) {
         if let Attribute::Parsed(AttributeKind::DocComment { comment, .. }) = attr {
             _ = writeln!(&mut buf, "{comment}");
@@ -195,58 +425,3 @@ fn doc_string_internel_did(did: IDefId, tcx: TyCtxt) -> String {
     }
     buf
 }
-
-pub enum Writer {
-    BaseDir(PathBuf),
-    Stdout,
-}
-
-impl Writer {
-    pub fn new(crate_name: &str) -> Self {
-        match base_dir(crate_name) {
-            Some(dir) => {
-                match fs::create_dir_all(&dir) {
-                    Ok(()) => (),
-                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
-                    Err(err) => panic!("The directory {dir:?} is not created: {err}"),
-                }
-                Writer::BaseDir(dir)
-            }
-            None => Writer::Stdout,
-        }
-    }
-
-    fn dump_json(&self, parent: &str, fname_stem: &str, data: &impl Serialize) {
-        match self {
-            Writer::BaseDir(dir) => {
-                let parent = dir.join(parent);
-                match fs::create_dir(&parent) {
-                    Ok(()) => (),
-                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
-                    Err(err) => panic!("The directory {dir:?} is not created: {err}"),
-                }
-
-                let mut file_path = parent.join(fname_stem);
-                file_path.set_extension("json");
-
-                let file = fs::File::create(&file_path).unwrap();
-                serde_json::to_writer_pretty(file, data).unwrap();
-            }
-            Writer::Stdout => {
-                use io::Write;
-                let stdout = &mut io::stdout();
-                _ = writeln!(stdout);
-                serde_json::to_writer_pretty(&mut *stdout, data).unwrap();
-                _ = writeln!(stdout);
-            }
-        }
-    }
-}
-
-/// The base directory `$UPG_DIR/crate_name` to store JSONs data.
-/// If the environment variable `UPG_DIR` is not set, data will be printed to stdout.
-pub fn base_dir(crate_name: &str) -> Option<PathBuf> {
-    std::env::var("UPG_DIR")
-        .ok()
-        .map(|dir| PathBuf::from(dir).join(crate_name))
-}
@@ -0,0 +1,51 @@
+//! Custom analysis passes.
+//!
+//! A pass sees every function with a body while the crate is analyzed, and the
+//! whole graph at the end. Its results are stored in `passes.<name>` of node
+//! JSON, so passes don't clash with each other or with built-in fields.
+//!
+//! Passes are registered by a custom driver built on the library:
+//!
+//! ```ignore
+//! #![feature(rustc_private)]
+//!
+//! fn main() {
+//!     unsafety_propagation_graph::driver::main(vec![Box::new(MyPass::default())]);
+//! }
+//! ```
+
+use crate::{graph::Graph, info_fn::FnInfo, utils::FxIndexMap};
+use rustc_middle::ty::TyCtxt;
+use rustc_public::{mir::Body, ty::FnDef};
+use serde_json::Value;
+
+pub trait Pass: Send {
+    /// The key of results in node JSON. It should be unique among passes.
+    fn name(&self) -> &str;
+
+    /// Called for each function with a body. The result is stored in the
+    /// function node if it's emitted.
+    fn function(
+        &mut self,
+        _fn_def: FnDef,
+        _body: &Body,
+        _info: &FnInfo,
+        _tcx: TyCtxt,
+    ) -> Option<Value> {
+        None
+    }
+
+    /// Called once after all nodes are built. Results are keyed by node IDs,
+    /// and replace results of [`Pass::function`] for the same node. IDs not in
+    /// the graph are ignored.
+    fn finish(&mut self, _graph: &Graph, _tcx: TyCtxt) -> FxIndexMap<String, Value> {
+        FxIndexMap::default()
+    }
+
+    /// Whether [`Pass::finish`] returns results. Nodes are held back until
+    /// such passes finish, and streamed to the sink as soon as they're
+    /// analyzed otherwise.
+    fn finish_results(&self) -> bool {
+        true
+    }
+}
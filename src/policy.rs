@@ -0,0 +1,221 @@
+//! Policies on the graph, checked in next to the crate as `upg-policy.toml`.
+//!
+//! ```toml
+//! # Public safe functions may only reach unsafe functions in these modules.
+//! unsafe_modules = ["*::raw"]
+//! # The most calls to unsafe functions in the crate.
+//! max_unsafe_calls = 20
+//! # Every unsafe function has a `# Safety` section in its doc.
+//! safety_doc = true
+//!
+//! [[allow]]
+//! id = "bf8381ef2dd40936aad875eb8f9d3a8e"
+//! rule = "unsafe-modules"
+//! reason = "reviewed in #123"
+//! ```
+//!
+//! Rules are off unless set. Allowed nodes are exempt from the rule, or from
+//! all rules if no rule is given. Allowed call sites don't count towards
+//! `max_unsafe_calls`.
+
+use crate::{
+    config::glob_match,
+    graph::{Graph, Node},
+    model::safety_doc,
+    query::NodeRef,
+    utils::{FxHashMap, FxIndexSet},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt::Write, fs, path::Path};
+
+/// The file discovered from the crate root.
+pub const POLICY_FILE: &str = "upg-policy.toml";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Module patterns where `*` matches any string. A module matches if it
+    /// or any of its parents matches, so `*::raw` covers `krate::raw::ffi`.
+    pub unsafe_modules: Option<Vec<String>>,
+    pub max_unsafe_calls: Option<usize>,
+    pub safety_doc: bool,
+    pub allow: Vec<Allow>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Allow {
+    /// The node ID.
+    pub id: String,
+    /// None for all rules.
+    #[serde(default)]
+    pub rule: Option<Rule>,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    UnsafeModules,
+    MaxUnsafeCalls,
+    SafetyDoc,
+}
+
+impl Rule {
+    /// The name in policy files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnsafeModules => "unsafe-modules",
+            Rule::MaxUnsafeCalls => "max-unsafe-calls",
+            Rule::SafetyDoc => "safety-doc",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Violation {
+    pub rule: Rule,
+    /// None for rules on the whole crate.
+    pub node: Option<NodeRef>,
+    /// Unsafe functions reached for `unsafe-modules`, or unsafe callers for
+    /// `max-unsafe-calls`.
+    pub related: Vec<NodeRef>,
+    pub message: String,
+}
+
+impl Policy {
+    pub fn from_file(path: &Path) -> Result<Policy, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path:?}: {err}"))?;
+        toml::from_str(&text).map_err(|err| format!("{path:?}: {err}"))
+    }
+
+    fn allows(&self, rule: Rule, id: &str) -> bool {
+        self.allow
+            .iter()
+            .any(|a| a.id == id && a.rule.is_none_or(|r| r == rule))
+    }
+
+    /// Violations in the order of rules, then nodes.
+    pub fn check(&self, graph: &Graph) -> Vec<Violation> {
+        let nodes = graph.node_map();
+        let mut violations = Vec::new();
+
+        if let Some(modules) = &self.unsafe_modules {
+            let allowed_module = |node: Node| {
+                let module = node.module();
+                let parents = module.match_indices("::").map(|(i, _)| &module[..i]);
+                parents
+                    .chain([module])
+                    .any(|m| modules.iter().any(|pattern| glob_match(pattern, m)))
+            };
+            let public = graph.functions.iter().filter(|f| f.public && f.safe);
+            for f in public.filter(|f| !self.allows(Rule::UnsafeModules, &f.id)) {
+                let reached = reach_unsafe(graph, &nodes, &f.id, allowed_module);
+                if !reached.is_empty() {
+                    violations.push(Violation {
+                        rule: Rule::UnsafeModules,
+                        node: Some(NodeRef::new(graph, &f.id)),
+                        related: reached.iter().map(|id| NodeRef::new(graph, id)).collect(),
+                        message: format!(
+                            "public safe fn `{}` reaches unsafe fns outside {}",
+                            f.name,
+                            modules.join(", ")
+                        ),
+                    });
+                }
+            }
+        }
+
+        if let Some(max) = self.max_unsafe_calls {
+            let mut callers = FxIndexSet::default();
+            let mut count = 0;
+            for f in &graph.functions {
+                if self.allows(Rule::MaxUnsafeCalls, &f.id) {
+                    continue;
+                }
+                let n = f.calls.iter().filter(|call| call.unsafe_callee).count();
+                if n > 0 {
+                    count += n;
+                    callers.insert(&*f.id);
+                }
+            }
+            if count > max {
+                violations.push(Violation {
+                    rule: Rule::MaxUnsafeCalls,
+                    node: None,
+                    related: callers.iter().map(|id| NodeRef::new(graph, id)).collect(),
+                    message: format!("{count} unsafe call sites exceed the maximum of {max}"),
+                });
+            }
+        }
+
+        if self.safety_doc {
+            let unsafe_fns = graph.functions.iter().filter(|f| !f.safe);
+            for f in unsafe_fns.filter(|f| !self.allows(Rule::SafetyDoc, &f.id)) {
+                if safety_doc(&f.doc).is_none() {
+                    violations.push(Violation {
+                        rule: Rule::SafetyDoc,
+                        node: Some(NodeRef::new(graph, &f.id)),
+                        related: Vec::new(),
+                        message: format!("unsafe fn `{}` has no `# Safety` doc", f.name),
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Unsafe nodes reachable from the function outside allowed modules. Nodes in
+/// allowed modules encapsulate what they call, so they're not followed.
+fn reach_unsafe<'a>(
+    graph: &'a Graph,
+    nodes: &FxHashMap<&str, Node<'a>>,
+    from: &'a str,
+    allowed_module: impl Fn(Node) -> bool,
+) -> FxIndexSet<&'a str> {
+    let functions: FxHashMap<&str, _> = graph.functions.iter().map(|f| (&*f.id, f)).collect();
+    let mut reached = FxIndexSet::default();
+    let mut visited = FxIndexSet::from_iter([from]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(id) = queue.pop_front() {
+        let Some(f) = functions.get(id) else {
+            continue;
+        };
+        for callee in &f.callees {
+            let Some(&node) = nodes.get(&**callee) else {
+                // Callees that aren't emitted have no module to allow them.
+                if f.unsafe_callee(callee) == Some(true) {
+                    reached.insert(&**callee);
+                }
+                continue;
+            };
+            if !visited.insert(callee) || allowed_module(node) {
+                continue;
+            }
+            if node.safe() == Some(false) {
+                reached.insert(&**callee);
+            }
+            queue.push_back(callee);
+        }
+    }
+    reached
+}
+
+/// One line per violation.
+pub fn to_text(violations: &[Violation]) -> String {
+    let mut buf = String::new();
+    for v in violations {
+        _ = write!(buf, "[{}] {}", v.rule.name(), v.message);
+        if let Some(node) = &v.node {
+            _ = write!(buf, " (id {})", node.id);
+        }
+        if !v.related.is_empty() {
+            let names: Vec<_> = v.related.iter().map(|node| &*node.name).collect();
+            _ = write!(buf, ": {}", names.join(", "));
+        }
+        buf.push('\n');
+    }
+    buf
+}
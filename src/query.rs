@@ -0,0 +1,399 @@
+//! Questions over a loaded graph, answered by `cargo upg query`.
+//!
+//! Functions are referred to by display names like `Type::method` or node IDs.
+//! A name may match several nodes, e.g. methods of different instantiations,
+//! and then all of them are answered.
+
+use crate::{
+    graph::{Graph, Node},
+    model::Function,
+    utils::{FxHashMap, FxIndexMap, FxIndexSet},
+};
+use serde::Serialize;
+use std::{collections::VecDeque, fmt::Write};
+
+#[derive(Clone, Debug)]
+pub enum Query {
+    /// Direct callers of the function.
+    Callers(String),
+    /// Transitive callees of the function.
+    Callees(String),
+    /// Functions writing the field, given as `Adt.field` or
+    /// `Adt.Variant.field`.
+    Writers(String),
+    /// The shortest call path from the function to an unsafe leaf, i.e. an
+    /// unsafe function not calling other unsafe functions. If None, a path is
+    /// searched from each public safe function.
+    Path(Option<String>),
+}
+
+impl Query {
+    /// Parse `callers FN`, `callees FN`, `writers ADT.FIELD` or `path [FN]`.
+    pub fn parse(args: &[String]) -> Result<Query, String> {
+        let arg = |name: &str| match args {
+            [_, arg] => Ok(arg.clone()),
+            _ => Err(format!("`{name}` takes one argument")),
+        };
+        match args.first().map(String::as_str) {
+            Some("callers") => arg("callers").map(Query::Callers),
+            Some("callees") => arg("callees").map(Query::Callees),
+            Some("writers") => arg("writers").map(Query::Writers),
+            Some("path") if args.len() == 1 => Ok(Query::Path(None)),
+            Some("path") => arg("path").map(|f| Query::Path(Some(f))),
+            Some(other) => Err(format!("unknown query `{other}`")),
+            None => Err("missing query".to_owned()),
+        }
+    }
+}
+
+/// A node by ID with its display name.
+#[derive(Clone, Debug, Serialize)]
+pub struct NodeRef {
+    pub id: String,
+    pub name: String,
+}
+
+impl NodeRef {
+    pub fn new(graph: &Graph, id: &str) -> NodeRef {
+        NodeRef {
+            id: id.to_owned(),
+            name: graph.name(id).to_owned(),
+        }
+    }
+}
+
+/// A call from a function. The span is empty if the call is not in `calls`,
+/// e.g. a call through a function pointer.
+#[derive(Clone, Debug, Serialize)]
+pub struct Site {
+    pub caller: NodeRef,
+    pub callee: NodeRef,
+    pub span: String,
+    /// The callee is unsafe.
+    #[serde(rename = "unsafe")]
+    pub unsafe_: bool,
+    pub unsafe_block: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Reached {
+    pub node: NodeRef,
+    /// The number of calls from the queried function.
+    pub depth: usize,
+    /// None if the callee is not in the output and has no call site.
+    pub safe: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Writer {
+    pub node: NodeRef,
+    pub public: bool,
+    pub safe: bool,
+    /// Access kinds like `MutRefVariantField`.
+    pub accesses: Vec<&'static str>,
+    pub span: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CallPath {
+    pub from: NodeRef,
+    /// Calls from `from` to the unsafe leaf. Empty if `from` is a leaf.
+    pub sites: Vec<Site>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "query", content = "results", rename_all = "lowercase")]
+pub enum Answer {
+    Callers(Vec<Site>),
+    Callees(Vec<Reached>),
+    Writers(Vec<Writer>),
+    Path(Vec<CallPath>),
+}
+
+/// Answer the query. Unknown functions and fields are errors.
+pub fn run(graph: &Graph, query: &Query) -> Result<Answer, String> {
+    let calls = CallGraph::new(graph);
+    Ok(match query {
+        Query::Callers(name) => {
+            let ids = calls.resolve(name)?;
+            let sites = graph.functions.iter().flat_map(|f| calls.sites(f));
+            let sites = sites.filter(|(callee, _)| ids.contains(callee));
+            Answer::Callers(sites.map(|(_, site)| site).collect::<Result<_, _>>()?)
+        }
+        Query::Callees(name) => Answer::Callees(callees(&calls, &calls.resolve(name)?)),
+        Query::Writers(field) => Answer::Writers(writers(graph, field)?),
+        Query::Path(Some(name)) => {
+            let from = calls.resolve(name)?;
+            let paths = from.iter().map(|id| calls.path(id));
+            Answer::Path(paths.filter_map(Result::transpose).collect::<Result<_, _>>()?)
+        }
+        Query::Path(None) => {
+            let from = graph.functions.iter().filter(|f| f.public && f.safe);
+            let paths = from.map(|f| calls.path(&f.id));
+            Answer::Path(paths.filter_map(Result::transpose).collect::<Result<_, _>>()?)
+        }
+    })
+}
+
+/// Call edges of a graph with node lookups.
+struct CallGraph<'a> {
+    graph: &'a Graph,
+    nodes: FxHashMap<&'a str, Node<'a>>,
+    functions: FxHashMap<&'a str, &'a Function>,
+}
+
+impl<'a> CallGraph<'a> {
+    fn new(graph: &'a Graph) -> CallGraph<'a> {
+        CallGraph {
+            graph,
+            nodes: graph.node_map(),
+            functions: graph.functions.iter().map(|f| (&*f.id, f)).collect(),
+        }
+    }
+
+    fn node_ref(&self, id: &str) -> NodeRef {
+        NodeRef::new(self.graph, id)
+    }
+
+    /// None if the node is not in the graph.
+    fn safe(&self, id: &str) -> Option<bool> {
+        self.nodes.get(id).and_then(|node| node.safe())
+    }
+
+    /// IDs of the name or the ID.
+    fn resolve(&self, name: &str) -> Result<FxIndexSet<&'a str>, String> {
+        let ids: FxIndexSet<&str> = self
+            .graph
+            .names
+            .iter()
+            .filter(|(id, n)| *n == name || *id == name)
+            .map(|(id, _)| id.as_str())
+            .collect();
+        if ids.is_empty() {
+            return Err(format!("no function named `{name}`"));
+        }
+        Ok(ids)
+    }
+
+    /// Whether the callee of the function is unsafe. It's recorded at call
+    /// sites, and looked up in nodes for callees without a site. An error if
+    /// neither is known, instead of taking the callee as safe.
+    fn unsafe_callee(&self, func: &Function, callee: &str) -> Result<bool, String> {
+        func.unsafe_callee(callee)
+            .or_else(|| self.safe(callee).map(|safe| !safe))
+            .ok_or_else(|| {
+                format!(
+                    "`{}` called by `{}` is not in the output, so its safety is unknown",
+                    self.graph.name(callee),
+                    func.name
+                )
+            })
+    }
+
+    /// Callee IDs with call sites in order, followed by callees without a site.
+    fn sites(&self, func: &'a Function) -> Vec<(&'a str, Result<Site, String>)> {
+        let site = |callee: &str, span: &str, unsafe_block: bool| {
+            Ok(Site {
+                caller: self.node_ref(&func.id),
+                callee: self.node_ref(callee),
+                span: span.to_owned(),
+                unsafe_: self.unsafe_callee(func, callee)?,
+                unsafe_block,
+            })
+        };
+        let mut sites: Vec<_> = func
+            .calls
+            .iter()
+            .map(|call| {
+                let site = site(&call.callee, &call.span, call.unsafe_block);
+                (&*call.callee, site)
+            })
+            .collect();
+        for callee in &func.callees {
+            if !func.calls.iter().any(|call| call.callee == *callee) {
+                sites.push((callee, site(callee, "", false)));
+            }
+        }
+        sites
+    }
+
+    /// Unsafe functions whose callees are all safe. `unsafe_` is whether the
+    /// function is unsafe, which is known at call sites even if it's not in
+    /// the output.
+    fn is_unsafe_leaf(&self, id: &str, unsafe_: bool) -> Result<bool, String> {
+        if !unsafe_ {
+            return Ok(false);
+        }
+        let Some(func) = self.functions.get(id) else {
+            return Ok(true);
+        };
+        for callee in &func.callees {
+            if self.unsafe_callee(func, callee)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The shortest path by breadth-first search. None if no unsafe leaf is
+    /// reachable.
+    fn path(&self, from: &str) -> Result<Option<CallPath>, String> {
+        // Nodes keyed by IDs, with the caller and the site reaching them first.
+        let mut visited = FxIndexMap::<&str, Option<(&str, Site)>>::default();
+        visited.insert(from, None);
+        let mut queue = VecDeque::from([(from, self.safe(from) == Some(false))]);
+        while let Some((id, unsafe_)) = queue.pop_front() {
+            if self.is_unsafe_leaf(id, unsafe_)? {
+                let mut sites = Vec::new();
+                let mut cur = id;
+                while let Some(Some((caller, site))) = visited.get(cur) {
+                    sites.push(site.clone());
+                    cur = caller;
+                }
+                sites.reverse();
+                return Ok(Some(CallPath {
+                    from: self.node_ref(from),
+                    sites,
+                }));
+            }
+            let Some(func) = self.functions.get(id) else {
+                continue;
+            };
+            for (callee, site) in self.sites(func) {
+                if !visited.contains_key(callee) {
+                    let site = site?;
+                    queue.push_back((callee, site.unsafe_));
+                    visited.insert(callee, Some((id, site)));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn callees(calls: &CallGraph, from: &FxIndexSet<&str>) -> Vec<Reached> {
+    // Depths and safety keyed by IDs. The safety is recorded at the first
+    // call site reaching the callee.
+    let mut reached = FxIndexMap::<&str, (usize, Option<bool>)>::default();
+    let mut queue: VecDeque<(&str, usize)> = from.iter().map(|id| (*id, 0)).collect();
+    while let Some((id, depth)) = queue.pop_front() {
+        let Some(func) = calls.functions.get(id) else {
+            continue;
+        };
+        for callee in &func.callees {
+            if !from.contains(&**callee) && !reached.contains_key(&**callee) {
+                let safe = calls.unsafe_callee(func, callee).ok().map(|unsafe_| !unsafe_);
+                reached.insert(callee, (depth + 1, safe));
+                queue.push_back((callee, depth + 1));
+            }
+        }
+    }
+    reached
+        .into_iter()
+        .map(|(id, (depth, safe))| Reached {
+            node: calls.node_ref(id),
+            depth,
+            safe,
+        })
+        .collect()
+}
+
+/// Accesses that may write the field, matching `Access::write` of adts.
+fn writers(graph: &Graph, field: &str) -> Result<Vec<Writer>, String> {
+    let (adt_name, field_name) = field
+        .split_once('.')
+        .ok_or_else(|| format!("`{field}` is not in the form of `Adt.field`"))?;
+    // Generic adts are named like `Adt<T>`.
+    let adts: FxIndexSet<&str> = graph
+        .adts
+        .iter()
+        .filter(|a| a.name == adt_name || a.name.split('<').next() == Some(adt_name))
+        .map(|a| &*a.id)
+        .collect();
+    if adts.is_empty() {
+        return Err(format!("no adt named `{adt_name}`"));
+    }
+
+    let mut writers = Vec::new();
+    for func in &graph.functions {
+        let accesses: FxIndexSet<&'static str> = func
+            .adts
+            .iter()
+            .filter(|(adt, _)| adts.contains(adt.as_str()))
+            .flat_map(|(_, accesses)| accesses)
+            .filter(|access| {
+                access.is_write()
+                    && access
+                        .variant_field()
+                        .is_some_and(|vf| vf.name() == field_name)
+            })
+            .map(|access| access.kind())
+            .collect();
+        if !accesses.is_empty() {
+            writers.push(Writer {
+                node: NodeRef::new(graph, &func.id),
+                public: func.public,
+                safe: func.safe,
+                accesses: accesses.into_iter().collect(),
+                span: func.span.clone(),
+            });
+        }
+    }
+    Ok(writers)
+}
+
+impl Answer {
+    /// One line per result, or per call in paths.
+    pub fn to_text(&self) -> String {
+        let mut buf = String::new();
+        match self {
+            Answer::Callers(sites) => {
+                for site in sites {
+                    _ = writeln!(buf, "{} -> {}", site.caller.name, callee(site));
+                }
+            }
+            Answer::Callees(reached) => {
+                for r in reached {
+                    let safety = match r.safe {
+                        Some(true) => "",
+                        Some(false) => " (unsafe)",
+                        None => " (unknown)",
+                    };
+                    _ = writeln!(buf, "{} {}{safety}", r.depth, r.node.name);
+                }
+            }
+            Answer::Writers(writers) => {
+                for w in writers {
+                    let vis = if w.public { "pub " } else { "" };
+                    let safety = if w.safe { "safe" } else { "unsafe" };
+                    let accesses = w.accesses.join(", ");
+                    _ = writeln!(buf, "{vis}{safety} {} [{accesses}] {}", w.node.name, w.span);
+                }
+            }
+            Answer::Path(paths) => {
+                for path in paths {
+                    _ = writeln!(buf, "{}", path.from.name);
+                    for site in &path.sites {
+                        _ = writeln!(buf, "  -> {}", callee(site));
+                    }
+                }
+            }
+        }
+        buf
+    }
+}
+
+/// The callee name with the safety and the call site.
+fn callee(site: &Site) -> String {
+    let mut buf = site.callee.name.clone();
+    if site.unsafe_ {
+        buf.push_str(" (unsafe)");
+    }
+    if !site.span.is_empty() {
+        _ = write!(buf, " at {}", site.span);
+    }
+    if site.unsafe_block {
+        buf.push_str(" in unsafe block");
+    }
+    buf
+}
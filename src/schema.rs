@@ -0,0 +1,173 @@
+//! JSON Schema and TypeScript definitions of the output format.
+//!
+//! Both are derived from the output types, so the driver and the UI can't drift
+//! apart. Run `unsafety-propagation-graph --upg-export-schema=.` in the repo
+//! root to regenerate `schema/*.schema.json` and `ui/app/lib/schema.ts`.
+
+use crate::{
+    model::{Adt, Function, Index, Names, SCHEMA_VERSION, Stub, Summary},
+    utils::FxIndexMap,
+};
+use schemars::{JsonSchema, Schema, generate::SchemaSettings};
+use serde_json::Value;
+use std::{fmt::Write, fs, io, path::Path};
+
+/// Schemas of output files keyed by the file kind.
+fn schemas() -> Vec<(&'static str, Schema)> {
+    fn root<T: JsonSchema>() -> Schema {
+        let settings = SchemaSettings::draft2020_12().for_serialize();
+        settings.into_generator().into_root_schema_for::<T>()
+    }
+    vec![
+        ("function", root::<Function>()),
+        ("adt", root::<Adt>()),
+        ("stub", root::<Stub>()),
+        ("summary", root::<Summary>()),
+        ("index", root::<Index>()),
+        ("names", root::<Names>()),
+    ]
+}
+
+pub fn export(root: &Path) -> io::Result<()> {
+    let schema_dir = root.join("schema");
+    fs::create_dir_all(&schema_dir)?;
+
+    // Definitions shared by schemas are emitted once in TypeScript.
+    let mut defs = FxIndexMap::<String, Value>::default();
+    for (kind, schema) in schemas() {
+        let mut value = schema.to_value();
+        if let Some(Value::Object(map)) = value.as_object_mut().and_then(|v| v.remove("$defs")) {
+            defs.extend(map);
+        }
+        let title = value["title"].as_str().unwrap_or(kind).to_owned();
+        defs.insert(title, value.clone());
+
+        let json = serde_json::to_string_pretty(&value)? + "\n";
+        fs::write(schema_dir.join(format!("{kind}.schema.json")), json)?;
+    }
+
+    let mut ts = format!(
+        "// Generated from the output types by `unsafety-propagation-graph --upg-export-schema`.\n\
+         // Don't edit by hand.\n\n\
+         export const SCHEMA_VERSION = {SCHEMA_VERSION};\n"
+    );
+    for (name, schema) in &defs {
+        ts.push('\n');
+        ts_doc(&mut ts, schema, "");
+        _ = writeln!(ts, "export type {name} = {};", ts_type(schema, ""));
+    }
+    fs::write(root.join("ui/app/lib/schema.ts"), ts)
+}
+
+fn ts_doc(buf: &mut String, schema: &Value, indent: &str) {
+    let Some(desc) = schema.get("description").and_then(Value::as_str) else {
+        return;
+    };
+    _ = writeln!(buf, "{indent}/**");
+    for line in desc.lines() {
+        let line = format!("{indent} * {line}");
+        _ = writeln!(buf, "{}", line.trim_end());
+    }
+    _ = writeln!(buf, "{indent} */");
+}
+
+/// Convert the subset of JSON Schema produced by schemars to a TypeScript type.
+fn ts_type(schema: &Value, indent: &str) -> String {
+    let Some(obj) = schema.as_object() else {
+        // `true` accepts anything.
+        return "unknown".to_owned();
+    };
+
+    // Constraints that all must hold.
+    let mut all = Vec::new();
+    if let Some(name) = obj.get("$ref").and_then(Value::as_str) {
+        all.push(name.trim_start_matches("#/$defs/").to_owned());
+    }
+    if let Some(c) = obj.get("const") {
+        all.push(c.to_string());
+    } else if let Some(Value::Array(v)) = obj.get("enum") {
+        all.push(union(v.iter().map(Value::to_string)));
+    } else if let Some(ty) = obj.get("type") {
+        let types = match ty {
+            Value::Array(v) => v.iter().filter_map(Value::as_str).collect(),
+            _ => ty.as_str().into_iter().collect::<Vec<_>>(),
+        };
+        all.push(union(
+            types.into_iter().map(|ty| ts_primitive(ty, obj, indent)),
+        ));
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(Value::Array(v)) = obj.get(key) {
+            all.push(union(v.iter().map(|s| paren(ts_type(s, indent)))));
+        }
+    }
+    if let Some(Value::Array(v)) = obj.get("allOf") {
+        all.extend(v.iter().map(|s| paren(ts_type(s, indent))));
+    }
+
+    match all.len() {
+        0 => "unknown".to_owned(),
+        1 => all.pop().unwrap(),
+        _ => all.into_iter().map(paren).collect::<Vec<_>>().join(" & "),
+    }
+}
+
+fn ts_primitive(ty: &str, obj: &serde_json::Map<String, Value>, indent: &str) -> String {
+    match ty {
+        "string" => "string".to_owned(),
+        "integer" | "number" => "number".to_owned(),
+        "boolean" => "boolean".to_owned(),
+        "null" => "null".to_owned(),
+        "array" => match obj.get("items") {
+            Some(items) => format!("{}[]", paren(ts_type(items, indent))),
+            None => "unknown[]".to_owned(),
+        },
+        "object" => ts_object(obj, indent),
+        _ => "unknown".to_owned(),
+    }
+}
+
+fn ts_object(obj: &serde_json::Map<String, Value>, indent: &str) -> String {
+    let required: Vec<_> = match obj.get("required") {
+        Some(Value::Array(v)) => v.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let inner = format!("{indent}  ");
+    let mut buf = String::from("{\n");
+    if let Some(Value::Object(props)) = obj.get("properties") {
+        for (name, prop) in props {
+            ts_doc(&mut buf, prop, &inner);
+            let optional = if required.contains(&name.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            _ = writeln!(buf, "{inner}{name}{optional}: {},", ts_type(prop, &inner));
+        }
+    }
+    match obj.get("additionalProperties") {
+        Some(Value::Bool(false)) | None => (),
+        Some(value) => {
+            _ = writeln!(buf, "{inner}[key: string]: {},", ts_type(value, &inner));
+        }
+    }
+    if buf == "{\n" {
+        return "{}".to_owned();
+    }
+    buf.push_str(indent);
+    buf.push('}');
+    buf
+}
+
+fn union(iter: impl Iterator<Item = String>) -> String {
+    iter.collect::<Vec<_>>().join(" | ")
+}
+
+/// Parenthesize compound types.
+fn paren(ty: String) -> String {
+    if !ty.starts_with('{') && (ty.contains(" | ") || ty.contains(" & ")) {
+        format!("({ty})")
+    } else {
+        ty
+    }
+}
@@ -0,0 +1,309 @@
+//! Output sinks.
+//!
+//! The driver feeds every sink the same sequence: `begin` once, `node` for
+//! each node as soon as it's analyzed, `edge` for each edge after all nodes,
+//! and `finish` with the whole graph. A format only implements the hooks it
+//! needs, so adding one doesn't touch `main::run`.
+
+use crate::{
+    config::{Config, Format},
+    datalog::Facts,
+    dot,
+    graph::{Edge, EdgeKind, Graph, Node},
+    graphml,
+    model::{AdtAccess, Index, Names},
+    sqlite,
+    utils::{FxHashMap, with_path},
+};
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+pub trait Sink {
+    fn begin(&mut self, _krate: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn node(&mut self, _node: Node) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Edges are only known after all nodes, because the safety of callees
+    /// decides whether calls are unsafe.
+    fn edge(&mut self, _edge: Edge) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self, _graph: &Graph) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sinks for the configured format. Data of the crate is written under
+/// `dir/dir_name` if the base directory is set, or printed to stdout.
+pub fn new(config: &Config, dir_name: &str) -> io::Result<Vec<Box<dyn Sink>>> {
+    let Some(dir) = config.crate_dir(dir_name) else {
+        let sink: Box<dyn Sink> = match config.format {
+            Format::Json => Box::new(JsonStdout),
+            Format::Ndjson => Box::new(Ndjson::new(Box::new(io::stdout()))),
+            Format::Dot => Box::new(Render::new(None, dot::render)),
+            Format::Graphml => Box::new(Render::new(None, graphml::render)),
+            // Rejected by `Config::from_args`, but configs may be built by hand.
+            Format::Sqlite | Format::Datalog => {
+                return Err(io::Error::other(
+                    "sqlite and datalog formats need --upg-dir",
+                ));
+            }
+        };
+        return Ok(vec![sink]);
+    };
+
+    fs::create_dir_all(&dir).map_err(with_path(&dir))?;
+    let sink: Box<dyn Sink> = match config.format {
+        Format::Json => Box::new(JsonDir { dir: dir.clone() }),
+        Format::Ndjson => {
+            let path = dir.join("upg.ndjson");
+            let file = fs::File::create(&path).map_err(with_path(&path))?;
+            Box::new(Ndjson::new(Box::new(BufWriter::new(file))))
+        }
+        Format::Dot => Box::new(DotDir { dir: dir.clone() }),
+        Format::Graphml => Box::new(Render::new(
+            Some(dir.join("graph.graphml")),
+            graphml::render,
+        )),
+        Format::Sqlite => Box::new(Sqlite {
+            path: dir.join("upg.sqlite"),
+        }),
+        Format::Datalog => Box::new(Datalog {
+            dir: dir.join("facts"),
+        }),
+    };
+    Ok(vec![sink, Box::new(SummaryFile { dir })])
+}
+
+impl Sink for Vec<Box<dyn Sink>> {
+    fn begin(&mut self, krate: &str) -> io::Result<()> {
+        self.iter_mut().try_for_each(|sink| sink.begin(krate))
+    }
+
+    fn node(&mut self, node: Node) -> io::Result<()> {
+        self.iter_mut().try_for_each(|sink| sink.node(node))
+    }
+
+    fn edge(&mut self, edge: Edge) -> io::Result<()> {
+        self.iter_mut().try_for_each(|sink| sink.edge(edge))
+    }
+
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        self.iter_mut().try_for_each(|sink| sink.finish(graph))
+    }
+}
+
+fn write_json(path: &Path, data: &impl Serialize) -> io::Result<()> {
+    let file = fs::File::create(path).map_err(with_path(path))?;
+    let mut file = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut file, data)?;
+    file.flush().map_err(with_path(path))
+}
+
+/// Pretty JSON files of each node in `kind/id.json`, the map from node IDs to
+/// display names in `names.json`, and the manifest in `index.json`.
+struct JsonDir {
+    dir: PathBuf,
+}
+
+impl Sink for JsonDir {
+    fn node(&mut self, node: Node) -> io::Result<()> {
+        let parent = self.dir.join(node.kind());
+        fs::create_dir_all(&parent).map_err(with_path(&parent))?;
+        write_json(&parent.join(format!("{}.json", node.id())), &node)
+    }
+
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        let names = Names::new(graph.names.clone());
+        write_json(&self.dir.join("names.json"), &names)?;
+        write_json(
+            &self.dir.join("index.json"),
+            &Index::new(graph, toolchain()),
+        )
+    }
+}
+
+fn toolchain() -> &'static str {
+    rustc_interface::util::rustc_version_str().unwrap_or("unknown")
+}
+
+/// Pretty JSON objects of each node separated by blank lines. Names are not
+/// printed, because each node carries its own name.
+struct JsonStdout;
+
+impl Sink for JsonStdout {
+    fn node(&mut self, node: Node) -> io::Result<()> {
+        let stdout = &mut io::stdout().lock();
+        writeln!(stdout)?;
+        serde_json::to_writer_pretty(&mut *stdout, &node)?;
+        writeln!(stdout)
+    }
+}
+
+/// Compact JSON records, one per line, streamed as the analysis progresses.
+struct Ndjson {
+    out: Box<dyn Write>,
+}
+
+/// A line of NDJSON. `kind` is `function`, `adt`, `stub`, `edge` or `summary`.
+/// `id` is the node ID, the source node of an edge, or the crate name of a
+/// summary. The object is nested in `data`, because adts have their own `kind`.
+#[derive(Serialize)]
+struct Record<'a, T> {
+    kind: &'a str,
+    id: &'a str,
+    data: &'a T,
+}
+
+#[derive(Serialize)]
+struct EdgeRecord<'a> {
+    source: &'a str,
+    target: &'a str,
+    /// `call` or `access`.
+    edge: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    access: Option<&'a AdtAccess>,
+    #[serde(rename = "unsafe")]
+    unsafe_: bool,
+}
+
+impl Ndjson {
+    fn new(out: Box<dyn Write>) -> Ndjson {
+        Ndjson { out }
+    }
+
+    fn record(&mut self, kind: &str, id: &str, data: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &Record { kind, id, data })?;
+        self.out.write_all(b"\n")
+    }
+}
+
+impl Sink for Ndjson {
+    fn node(&mut self, node: Node) -> io::Result<()> {
+        self.record(node.kind(), node.id(), &node)
+    }
+
+    fn edge(&mut self, edge: Edge) -> io::Result<()> {
+        let (edge_kind, access) = match edge.kind {
+            EdgeKind::Call => ("call", None),
+            EdgeKind::Access(access) => ("access", Some(access)),
+        };
+        let record = EdgeRecord {
+            source: edge.source,
+            target: edge.target,
+            edge: edge_kind,
+            access,
+            unsafe_: edge.unsafe_,
+        };
+        self.record("edge", edge.source, &record)
+    }
+
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        if let Some(summary) = &graph.summary {
+            self.record("summary", &graph.krate, summary)?;
+        }
+        self.out.flush()
+    }
+}
+
+/// A text rendering of the whole crate written to the file, or printed to
+/// stdout if the path is None.
+struct Render {
+    path: Option<PathBuf>,
+    render: fn(&Graph) -> String,
+}
+
+impl Render {
+    fn new(path: Option<PathBuf>, render: fn(&Graph) -> String) -> Render {
+        Render { path, render }
+    }
+}
+
+impl Sink for Render {
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        let text = (self.render)(graph);
+        match &self.path {
+            Some(path) => fs::write(path, text).map_err(with_path(path)),
+            None => io::stdout().lock().write_all(text.as_bytes()),
+        }
+    }
+}
+
+/// The whole crate in `graph.dot`, and the neighbourhood of each function in
+/// `dot/id.dot`.
+struct DotDir {
+    dir: PathBuf,
+}
+
+impl Sink for DotDir {
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        let path = self.dir.join("graph.dot");
+        fs::write(&path, dot::render(graph)).map_err(with_path(&path))?;
+        let parent = self.dir.join("dot");
+        fs::create_dir_all(&parent).map_err(with_path(&parent))?;
+        // Edges are grouped by their ends once, instead of filtered for each
+        // function.
+        let nodes = graph.node_map();
+        let mut neighbourhoods = FxHashMap::<&str, Vec<Edge>>::default();
+        for edge in graph.edges() {
+            neighbourhoods.entry(edge.source).or_default().push(edge);
+            if edge.target != edge.source {
+                neighbourhoods.entry(edge.target).or_default().push(edge);
+            }
+        }
+        for func in &graph.functions {
+            let path = parent.join(format!("{}.dot", func.id));
+            let edges = neighbourhoods.remove(&*func.id).unwrap_or_default();
+            let text = dot::render_neighbourhood(graph, &nodes, &func.id, edges);
+            fs::write(&path, text).map_err(with_path(&path))?;
+        }
+        Ok(())
+    }
+}
+
+struct Sqlite {
+    path: PathBuf,
+}
+
+impl Sink for Sqlite {
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        sqlite::write(graph, &self.path)
+            .map_err(|err| io::Error::other(format!("{}: {err}", self.path.display())))
+    }
+}
+
+/// Tab-separated facts in `facts/relation.facts`.
+struct Datalog {
+    dir: PathBuf,
+}
+
+impl Sink for Datalog {
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        Facts::new(graph)
+            .write(&self.dir)
+            .map_err(with_path(&self.dir))
+    }
+}
+
+/// The summary in `summary.json` for later crates, whatever the format is.
+struct SummaryFile {
+    dir: PathBuf,
+}
+
+impl Sink for SummaryFile {
+    fn finish(&mut self, graph: &Graph) -> io::Result<()> {
+        match &graph.summary {
+            Some(summary) => write_json(&self.dir.join("summary.json"), summary),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! SQLite output of the whole crate in normalized tables.
+//!
+//! For example, pub safe functions writing a field accessed by an unsafe
+//! function:
+//!
+//! ```sql
+//! SELECT DISTINCT w.name, field.name FROM access AS wa
+//! JOIN function AS w ON w.id = wa.fn_id
+//! JOIN field ON field.id = wa.field_id
+//! JOIN access AS ra ON ra.field_id = wa.field_id
+//! JOIN function AS r ON r.id = ra.fn_id
+//! WHERE wa.write AND w.public AND w.safe AND NOT r.safe;
+//! ```
+
+use crate::{
+    graph::{EdgeKind, Graph},
+    model::{AdtAccess, SCHEMA_VERSION, Span},
+};
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE meta (
+    schema_version INTEGER NOT NULL,
+    crate TEXT NOT NULL
+);
+CREATE TABLE span (
+    id INTEGER PRIMARY KEY,
+    file TEXT NOT NULL,
+    line_lo INTEGER,
+    col_lo INTEGER,
+    line_hi INTEGER,
+    col_hi INTEGER,
+    src TEXT NOT NULL
+);
+CREATE TABLE doc (
+    id INTEGER PRIMARY KEY,
+    text TEXT NOT NULL
+);
+-- Functions in the crate and stubs of external callees.
+CREATE TABLE function (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    module TEXT NOT NULL,
+    crate TEXT NOT NULL,
+    stub INTEGER NOT NULL,
+    safe INTEGER NOT NULL,
+    public INTEGER NOT NULL,
+    ffi_kind TEXT,
+    ffi_abi TEXT,
+    ffi_link_name TEXT,
+    span_id INTEGER REFERENCES span(id),
+    doc_id INTEGER REFERENCES doc(id)
+);
+CREATE TABLE adt (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    module TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    span_id INTEGER REFERENCES span(id),
+    doc_id INTEGER REFERENCES doc(id)
+);
+-- Struct and union fields, enum variants, and fields in enum variants.
+CREATE TABLE field (
+    id INTEGER PRIMARY KEY,
+    adt_id TEXT NOT NULL REFERENCES adt(id),
+    variant INTEGER,
+    field INTEGER,
+    name TEXT NOT NULL,
+    doc_id INTEGER REFERENCES doc(id),
+    UNIQUE (adt_id, variant, field)
+);
+CREATE TABLE call (
+    caller_id TEXT NOT NULL REFERENCES function(id),
+    callee_id TEXT NOT NULL,
+    unsafe INTEGER NOT NULL
+);
+-- field_id is null for accesses to the whole adt, and write is set if the
+-- access may write, like assignments and mutable borrows.
+CREATE TABLE access (
+    fn_id TEXT NOT NULL REFERENCES function(id),
+    adt_id TEXT NOT NULL,
+    field_id INTEGER REFERENCES field(id),
+    kind TEXT NOT NULL,
+    projection TEXT,
+    write INTEGER NOT NULL,
+    unsafe INTEGER NOT NULL
+);
+";
+
+/// Write the graph to a new database. An existing file is replaced.
+pub fn write(graph: &Graph, path: &Path) -> rusqlite::Result<()> {
+    if path.exists() {
+        _ = std::fs::remove_file(path);
+    }
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO meta VALUES (?1, ?2)",
+        params![SCHEMA_VERSION, graph.krate],
+    )?;
+
+    for f in &graph.functions {
+        let span_id = insert_span(&tx, &f.span, &f.src)?;
+        let doc_id = insert_doc(&tx, &f.doc)?;
+        let ffi = f.ffi.as_ref();
+        tx.execute(
+            "INSERT INTO function VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                f.id,
+                f.name,
+                f.module,
+                graph.krate,
+                f.safe,
+                f.public,
+                ffi.map(|ffi| format!("{:?}", ffi.kind)),
+                ffi.map(|ffi| &ffi.abi),
+                ffi.map(|ffi| &ffi.link_name),
+                span_id,
+                doc_id,
+            ],
+        )?;
+    }
+    for s in &graph.stubs {
+        let doc_id = insert_doc(&tx, &s.doc)?;
+        tx.execute(
+            "INSERT INTO function VALUES (?1, ?2, ?3, ?4, 1, ?5, 1, NULL, NULL, NULL, NULL, ?6)",
+            params![s.id, s.name, s.module, s.krate, s.safe, doc_id],
+        )?;
+    }
+    for a in &graph.adts {
+        let span_id = insert_span(&tx, &a.span, &a.src)?;
+        let doc_id = insert_doc(&tx, &a.doc_adt)?;
+        tx.execute(
+            "INSERT INTO adt VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![a.id, a.name, a.module, a.kind, span_id, doc_id],
+        )?;
+        for vf in a.variant_fields.values() {
+            let doc_id = insert_doc(&tx, &vf.doc)?;
+            tx.execute(
+                "INSERT INTO field (adt_id, variant, field, name, doc_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![a.id, vf.variant, vf.field, vf.name, doc_id],
+            )?;
+        }
+    }
+
+    for edge in graph.edges() {
+        match edge.kind {
+            EdgeKind::Call => {
+                tx.execute(
+                    "INSERT INTO call VALUES (?1, ?2, ?3)",
+                    params![edge.source, edge.target, edge.unsafe_],
+                )?;
+            }
+            EdgeKind::Access(access) => {
+                let vf = access.variant_field();
+                let field_id: Option<i64> = match vf {
+                    Some(vf) => tx
+                        .query_row(
+                            "SELECT id FROM field WHERE adt_id = ?1 AND variant IS ?2 AND field IS ?3",
+                            params![edge.target, vf.variant, vf.field],
+                            |row| row.get(0),
+                        )
+                        .optional()?,
+                    None => None,
+                };
+                let projection = match access {
+                    AdtAccess::Unknown { projection } => Some(projection),
+                    _ => None,
+                };
+                tx.execute(
+                    "INSERT INTO access VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        edge.source,
+                        edge.target,
+                        field_id,
+                        access.kind(),
+                        projection,
+                        access.is_write(),
+                        edge.unsafe_
+                    ],
+                )?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+/// Spans that can't be parsed are stored as the file without positions.
+fn insert_span(tx: &Transaction, span: &str, src: &str) -> rusqlite::Result<Option<i64>> {
+    if span.is_empty() {
+        return Ok(None);
+    }
+    let parsed = Span::parse(span);
+    let pos = |f: fn(&Span) -> u32| parsed.as_ref().map(f);
+    tx.execute(
+        "INSERT INTO span (file, line_lo, col_lo, line_hi, col_hi, src) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            parsed.as_ref().map_or(span, |s| &s.file),
+            pos(|s| s.line_lo),
+            pos(|s| s.col_lo),
+            pos(|s| s.line_hi),
+            pos(|s| s.col_hi),
+            src
+        ],
+    )?;
+    Ok(Some(tx.last_insert_rowid()))
+}
+
+fn insert_doc(tx: &Transaction, doc: &str) -> rusqlite::Result<Option<i64>> {
+    if doc.is_empty() {
+        return Ok(None);
+    }
+    tx.execute("INSERT INTO doc (text) VALUES (?1)", params![doc])?;
+    Ok(Some(tx.last_insert_rowid()))
+}
@@ -0,0 +1,128 @@
+//! Per-crate summaries for dependent crates. The types are in [`crate::model`].
+
+use crate::{
+    config::Config,
+    id::def_hash,
+    info_fn::FnInfo,
+    model::{FnSummary, SCHEMA_VERSION, Summary},
+    utils::{FxHashMap, FxIndexMap, FxIndexSet},
+};
+use rustc_middle::ty::TyCtxt;
+use rustc_public::{
+    CrateDef,
+    mir::{Safety, mono::Instance},
+    ty::FnDef,
+};
+use std::{fs, path::PathBuf};
+
+impl Summary {
+    pub fn new(
+        krate: &str,
+        map_fn: &FxIndexMap<FnDef, FnInfo>,
+        deps: &mut Dependencies,
+        tcx: TyCtxt,
+    ) -> Summary {
+        let mut functions = FxIndexMap::with_capacity_and_hasher(map_fn.len(), Default::default());
+        for (fn_def, info) in map_fn {
+            let safe = matches!(fn_def.fn_sig().value.safety, Safety::Safe);
+            let callees: FxIndexSet<_> = info
+                .callees
+                .iter()
+                .map(|callee| def_hash(callee.def.def_id(), tcx))
+                .collect();
+            let summary = FnSummary {
+                name: fn_def.name(),
+                safe,
+                callees: callees.into_iter().collect(),
+                reach_unsafe: !safe,
+            };
+            functions.insert(def_hash(fn_def.def_id(), tcx), summary);
+        }
+
+        // Whether each callee outside the map reaches unsafe code. It's fixed
+        // during propagation below.
+        let mut outside = FxHashMap::<String, bool>::default();
+        for info in map_fn.values() {
+            for callee in &info.callees {
+                let key = def_hash(callee.def.def_id(), tcx);
+                if functions.contains_key(&key) || outside.contains_key(&key) {
+                    continue;
+                }
+                let reach_unsafe = match deps.get(&callee.def.krate().name, &key) {
+                    Some(summary) => summary.reach_unsafe,
+                    None => !is_safe(callee),
+                };
+                outside.insert(key, reach_unsafe);
+            }
+        }
+
+        // Propagate unsafety from callees to callers until a fixpoint.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for idx in 0..functions.len() {
+                if functions[idx].reach_unsafe {
+                    continue;
+                }
+                let reach_unsafe = functions[idx].callees.iter().any(|key| {
+                    functions
+                        .get(key)
+                        .map(|f| f.reach_unsafe)
+                        .or_else(|| outside.get(key).copied())
+                        .unwrap_or(false)
+                });
+                if reach_unsafe {
+                    functions[idx].reach_unsafe = true;
+                    changed = true;
+                }
+            }
+        }
+
+        Summary {
+            schema_version: SCHEMA_VERSION,
+            krate: krate.to_owned(),
+            functions,
+        }
+    }
+}
+
+/// Summaries of dependency crates loaded from the base directory on demand.
+pub struct Dependencies {
+    /// None if the base directory is not set, or loading is turned off.
+    dir: Option<PathBuf>,
+    map: FxHashMap<String, Option<Summary>>,
+}
+
+impl Dependencies {
+    pub fn new(config: &Config) -> Dependencies {
+        Dependencies {
+            dir: config.dir.clone().filter(|_| config.analysis.deps),
+            map: FxHashMap::default(),
+        }
+    }
+
+    /// Look up a function in the summary of the given crate. Returns None if
+    /// the crate has not been analyzed, or the function is not in it.
+    pub fn get(&mut self, krate: &str, key: &str) -> Option<&FnSummary> {
+        self.map
+            .entry(krate.to_owned())
+            .or_insert_with(|| load(self.dir.as_ref()?.join(krate)))
+            .as_ref()?
+            .functions
+            .get(key)
+    }
+}
+
+/// Summaries written in other schema versions are ignored.
+fn load(crate_dir: PathBuf) -> Option<Summary> {
+    let json = fs::read_to_string(crate_dir.join("summary.json")).ok()?;
+    let summary: Summary = serde_json::from_str(&json).ok()?;
+    (summary.schema_version == SCHEMA_VERSION).then_some(summary)
+}
+
+/// The signature is taken from the instance, because closures have none by
+/// their def id. Instances without a signature, like coroutines, are safe.
+pub fn is_safe(instance: &Instance) -> bool {
+    let sig = instance.ty().kind().fn_sig();
+    sig.is_none_or(|sig| matches!(sig.value.safety, Safety::Safe))
+}
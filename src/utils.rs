@@ -1,10 +1,14 @@
-use rustc_data_structures::fx::FxHasher;
-use std::hash::BuildHasherDefault;
+use std::{hash::BuildHasherDefault, io, path::Path};
+
+pub use rustc_hash::{FxHashMap, FxHasher};
 
 pub type FxIndexSet<V> = indexmap::IndexSet<V, BuildHasherDefault<FxHasher>>;
 pub type FxIndexMap<K, V> = indexmap::IndexMap<K, V, BuildHasherDefault<FxHasher>>;
 
-pub use rustc_data_structures::fx::FxHashMap;
+#[cfg(feature = "driver")]
+pub use rustc_data_structures::{smallvec::SmallVec, thin_vec::ThinVec};
 
-pub use rustc_data_structures::smallvec::SmallVec;
-pub use rustc_data_structures::thin_vec::ThinVec;
+/// Prefix errors with the path.
+pub fn with_path(path: &Path) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |err| io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
@@ -0,0 +1,557 @@
+#![feature(rustc_private)]
+
+extern crate rustc_middle;
+extern crate rustc_public;
+
+use rustc_middle::ty::TyCtxt;
+use rustc_public::{mir::Body, ty::FnDef};
+use serde_json::{Value, json};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+use unsafety_propagation_graph::{
+    analyze,
+    audit::{Audit, Entry, Ledger},
+    config::{Config, Format},
+    datalog::Facts,
+    diff::Diff,
+    graph::{EdgeKind, Graph},
+    impact::{self, Impact},
+    info_fn::FnInfo,
+    model::{AdtAccess, Span},
+    pass::Pass,
+    policy::{Policy, Rule},
+    query::{self, Answer, Query},
+    schema, sink, sqlite,
+    utils::FxIndexMap,
+};
+
+const SRC: &str = include_str!("pass/dot.rs");
+
+#[test]
+fn source() {
+    let graph = analyze::source(SRC, &["--crate-name=dot"], &Config::default(), &mut []).unwrap();
+    assert_eq!(graph.krate, "dot");
+    assert_eq!(graph.functions.len(), 5);
+
+    let get = graph
+        .functions
+        .iter()
+        .find(|f| f.name == "buf::Buf::get")
+        .unwrap();
+    assert!(get.safe && get.public);
+    let [call] = &get.calls[..] else {
+        panic!("{:?}", get.calls);
+    };
+    assert_eq!(graph.name(&call.callee), "buf::Buf::get_unchecked");
+    assert!(call.unsafe_block);
+    assert_eq!(Span::parse(&call.span).unwrap().line_lo, 29);
+
+    let unsafe_calls: Vec<_> = graph
+        .edges()
+        .into_iter()
+        .filter(|edge| matches!(edge.kind, EdgeKind::Call) && edge.unsafe_)
+        .map(|edge| (graph.name(edge.source), graph.name(edge.target)))
+        .collect();
+    assert_eq!(
+        unsafe_calls,
+        [
+            ("buf::Buf::as_slice", "std::slice::from_raw_parts::<'_, u8>"),
+            (
+                "buf::Buf::get_unchecked",
+                "std::ptr::mut_ptr::<impl *mut u8>::add"
+            ),
+            ("buf::Buf::get", "buf::Buf::get_unchecked"),
+        ]
+    );
+
+    // Calls stay unsafe when their callees are not emitted.
+    let config = Config {
+        exclude: vec!["*::get_unchecked".to_owned(), "std::*".to_owned()],
+        ..Config::default()
+    };
+    let filtered = analyze::source(SRC, &["--crate-name=dot"], &config, &mut []).unwrap();
+    let unsafe_calls: Vec<_> = filtered
+        .edges()
+        .into_iter()
+        .filter(|edge| matches!(edge.kind, EdgeKind::Call) && edge.unsafe_)
+        .map(|edge| (filtered.name(edge.source), filtered.name(edge.target)))
+        .collect();
+    assert_eq!(
+        unsafe_calls,
+        [
+            ("buf::Buf::as_slice", "std::slice::from_raw_parts::<'_, u8>"),
+            ("buf::Buf::get", "buf::Buf::get_unchecked"),
+        ]
+    );
+
+    let set_len = graph
+        .functions
+        .iter()
+        .find(|f| f.name == "buf::Buf::set_len")
+        .unwrap();
+    let writes = set_len
+        .adts
+        .values()
+        .flatten()
+        .filter_map(|access| match access {
+            AdtAccess::MutDerefVariantField(vf) => Some(vf.name()),
+            _ => None,
+        });
+    assert_eq!(writes.collect::<Vec<_>>(), ["len"]);
+
+    let summary = graph.summary.as_ref().unwrap();
+    let reach_unsafe: Vec<_> = summary
+        .functions
+        .values()
+        .filter(|f| f.reach_unsafe)
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(
+        reach_unsafe,
+        [
+            "buf::Buf::as_slice",
+            "buf::Buf::get_unchecked",
+            "buf::Buf::get"
+        ]
+    );
+}
+
+#[test]
+fn compile_error() {
+    let err =
+        analyze::source("fn f() -> u8 { 0u16 }", &[], &Config::default(), &mut []).unwrap_err();
+    assert!(err.to_string().ends_with("compilation failed"), "{err}");
+}
+
+#[test]
+fn sink_without_dir() {
+    for format in [Format::Sqlite, Format::Datalog] {
+        let config = Config {
+            format,
+            ..Default::default()
+        };
+        let err = sink::new(&config, "buf").err().unwrap();
+        assert_eq!(err.to_string(), "sqlite and datalog formats need --upg-dir");
+    }
+}
+
+/// Counts calls per function, and functions per adt at the end.
+struct Count;
+
+impl Pass for Count {
+    fn name(&self) -> &str {
+        "count"
+    }
+
+    fn function(&mut self, _: FnDef, _: &Body, info: &FnInfo, _: TyCtxt) -> Option<Value> {
+        (!info.calls.is_empty()).then(|| json!({ "calls": info.calls.len() }))
+    }
+
+    fn finish(&mut self, graph: &Graph, _: TyCtxt) -> FxIndexMap<String, Value> {
+        graph
+            .adts
+            .iter()
+            .map(|adt| {
+                let fns = graph
+                    .functions
+                    .iter()
+                    .filter(|f| f.adts.contains_key(&adt.id))
+                    .count();
+                (adt.id.clone(), json!({ "functions": fns }))
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn pass() {
+    let mut passes: [Box<dyn Pass>; 1] = [Box::new(Count)];
+    let graph = analyze::source(SRC, &[], &Config::default(), &mut passes).unwrap();
+    let counts: Vec<_> = graph
+        .functions
+        .iter()
+        .map(|f| (f.name.as_str(), f.passes.get("count")))
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            ("buf::Buf::set_len", None),
+            ("buf::Buf::as_slice", Some(&json!({ "calls": 1 }))),
+            ("buf::Buf::get_unchecked", Some(&json!({ "calls": 1 }))),
+            ("buf::Buf::get", Some(&json!({ "calls": 1 }))),
+            ("to_bits", None),
+        ]
+    );
+    assert_eq!(graph.adts[0].passes["count"], json!({ "functions": 4 }));
+}
+
+#[test]
+fn query() {
+    let src = format!(
+        "{SRC}\nfn mid(buf: &buf::Buf) -> Option<u8> {{ buf.get(0) }}\npub fn top(buf: &buf::Buf) -> Option<u8> {{ mid(buf) }}"
+    );
+    let graph = analyze::source(&src, &[], &Config::default(), &mut []).unwrap();
+
+    let Answer::Path(paths) = query::run(&graph, &Query::Path(Some("top".into()))).unwrap() else {
+        unreachable!()
+    };
+    let hops: Vec<_> = paths[0]
+        .sites
+        .iter()
+        .map(|s| s.callee.name.as_str())
+        .collect();
+    assert_eq!(
+        hops,
+        [
+            "mid",
+            "buf::Buf::get",
+            "buf::Buf::get_unchecked",
+            "std::ptr::mut_ptr::<impl *mut u8>::add"
+        ]
+    );
+    assert!(paths[0].sites[2].unsafe_block);
+
+    let Answer::Writers(writers) =
+        query::run(&graph, &Query::Writers("buf::Buf.len".into())).unwrap()
+    else {
+        unreachable!()
+    };
+    let names: Vec<_> = writers.iter().map(|w| w.node.name.as_str()).collect();
+    // `get` only reads `len` through `&self`.
+    assert_eq!(names, ["buf::Buf::set_len"]);
+
+    let err = query::run(&graph, &Query::Callers("nope".into())).unwrap_err();
+    assert_eq!(err, "no function named `nope`");
+
+    // Unsafe callees not in the output are still leaves by their call sites.
+    let config = Config {
+        exclude: vec!["std::*".to_owned()],
+        ..Config::default()
+    };
+    let mut graph = analyze::source(&src, &[], &config, &mut []).unwrap();
+    let Answer::Path(paths) = query::run(&graph, &Query::Path(Some("top".into()))).unwrap() else {
+        unreachable!()
+    };
+    assert_eq!(paths[0].sites.len(), 4);
+    assert!(paths[0].sites[3].unsafe_);
+
+    // Without call sites, like calls through function pointers, their safety
+    // is unknown.
+    graph.functions.iter_mut().for_each(|f| f.calls.clear());
+    let err = query::run(&graph, &Query::Path(Some("top".into()))).unwrap_err();
+    assert_eq!(
+        err,
+        "`std::ptr::mut_ptr::<impl *mut u8>::add` called by `buf::Buf::get_unchecked` is not \
+         in the output, so its safety is unknown"
+    );
+}
+
+#[test]
+fn diff() {
+    let analyze = |src: &str| analyze::source(src, &[], &Config::default(), &mut []).unwrap();
+    let base = analyze(SRC);
+    let head = analyze(&SRC.replace(
+        "        pub unsafe fn get_unchecked",
+        "        /// # Safety\n        /// `idx` is in bounds.\n        pub unsafe fn get_unchecked",
+    ));
+    let diff = Diff::new(&base, &head);
+    assert_eq!(diff.safety_docs.len(), 1);
+    assert_eq!(
+        diff.safety_docs[0].head.as_deref(),
+        Some("`idx` is in bounds.")
+    );
+    assert!(diff.added_unsafe.is_empty() && diff.safe_writers.is_empty());
+
+    let head = analyze(&format!(
+        "{SRC}\npub fn peek(buf: &buf::Buf) -> u8 {{ unsafe {{ buf.get_unchecked(0) }} }}"
+    ));
+    let diff = Diff::new(&base, &head);
+    let calls: Vec<_> = diff
+        .unsafe_calls
+        .iter()
+        .map(|c| (c.caller.name.as_str(), c.callee.name.as_str()))
+        .collect();
+    assert_eq!(calls, [("peek", "buf::Buf::get_unchecked")]);
+    assert_eq!(
+        diff.to_text(),
+        "crate snippet\n  + unsafe call peek -> buf::Buf::get_unchecked\n"
+    );
+
+    let diff = Diff::new(&Graph::new("snippet"), &base);
+    let writers: Vec<_> = diff
+        .safe_writers
+        .iter()
+        .map(|w| (w.writer.name.as_str(), w.public))
+        .collect();
+    assert_eq!(writers, [("buf::Buf::set_len", true)]);
+    assert!(Diff::new(&base, &base).is_empty());
+
+    // A getter of a critical field only reads it.
+    let head = analyze(&SRC.replace(
+        "        pub fn get(",
+        "        pub fn len(&self) -> usize {\n            self.len\n        }\n\n        pub fn get(",
+    ));
+    assert!(Diff::new(&base, &head).is_empty());
+}
+
+#[test]
+fn impact() {
+    let graph = analyze::source(SRC, &[], &Config::default(), &mut []).unwrap();
+    // Changes the body of `Buf::get_unchecked`.
+    let patch = "\
+diff --git a/snippet.rs b/snippet.rs
+--- a/snippet.rs
++++ b/snippet.rs
+@@ -23,3 +23,3 @@ impl Buf {
+         pub unsafe fn get_unchecked(&self, idx: usize) -> u8 {
+-            unsafe { *self.ptr.add(idx) }
++            unsafe { *self.ptr.add(idx + 0) }
+         }
+";
+    let changed = impact::changed_lines(patch);
+    assert_eq!(changed["snippet.rs"], [24]);
+    let impact = Impact::new(&graph, &changed);
+    assert_eq!(
+        impact.to_text().lines().skip(1).collect::<Vec<_>>(),
+        [
+            format!(
+                "  unsafe function buf::Buf::get_unchecked {}",
+                impact.touched[0].span
+            ),
+            "  pub fn buf::Buf::get reaches buf::Buf::get_unchecked".to_owned(),
+        ]
+    );
+}
+
+#[test]
+fn audit() {
+    let analyze = |src: &str| analyze::source(src, &[], &Config::default(), &mut []).unwrap();
+    let graph = analyze(SRC);
+    let audit = Audit::new(&graph, &Ledger::default());
+    let unaudited: Vec<_> = audit
+        .unaudited
+        .iter()
+        .map(|u| (u.node.name.as_str(), u.safe))
+        .collect();
+    assert_eq!(
+        unaudited,
+        [
+            ("buf::Buf::as_slice", false),
+            ("buf::Buf::get_unchecked", false),
+            ("buf::Buf::get", true),
+            ("to_bits", true),
+        ]
+    );
+
+    let entries = audit.unaudited.iter().map(|u| Entry {
+        id: u.node.id.clone(),
+        name: u.node.name.clone(),
+        reviewer: "alice".into(),
+        date: "2026-10-19".into(),
+        hash: u.hash.clone(),
+        callees: u.callees.clone(),
+    });
+    let ledger = Ledger {
+        entries: entries.collect(),
+    };
+    assert!(Audit::new(&graph, &ledger).is_empty());
+
+    // Only the callee changes, but both audits are stale.
+    let graph = analyze(&SRC.replace("add(idx)", "add(idx + 0)"));
+    let audit = Audit::new(&graph, &ledger);
+    let stale: Vec<_> = audit
+        .stale
+        .iter()
+        .map(|s| (s.node.name.as_str(), s.source_changed, s.callees_changed))
+        .collect();
+    assert_eq!(
+        stale,
+        [
+            ("buf::Buf::get_unchecked", true, false),
+            ("buf::Buf::get", false, true)
+        ]
+    );
+}
+
+#[test]
+fn policy() {
+    let src = format!(
+        "{SRC}\npub mod raw {{ pub unsafe fn zero() -> usize {{ 0 }} }}\npub fn zero() -> usize {{ unsafe {{ raw::zero() }} }}"
+    );
+    let graph = analyze::source(&src, &[], &Config::default(), &mut []).unwrap();
+    let policy: Policy = toml::from_str(
+        r#"
+        unsafe_modules = ["*::raw"]
+        max_unsafe_calls = 3
+        safety_doc = true
+        "#,
+    )
+    .unwrap();
+    let violations: Vec<_> = policy
+        .check(&graph)
+        .into_iter()
+        .map(|v| (v.rule, v.node.map(|node| node.name)))
+        .collect();
+    assert_eq!(
+        violations,
+        [
+            (Rule::UnsafeModules, Some("buf::Buf::get".to_owned())),
+            (Rule::MaxUnsafeCalls, None),
+            (Rule::SafetyDoc, Some("buf::Buf::as_slice".to_owned())),
+            (Rule::SafetyDoc, Some("buf::Buf::get_unchecked".to_owned())),
+            (Rule::SafetyDoc, Some("raw::zero".to_owned())),
+        ]
+    );
+
+    // Allowing `get` also drops its call site below the maximum.
+    let get = graph
+        .functions
+        .iter()
+        .find(|f| f.name == "buf::Buf::get")
+        .unwrap();
+    let policy: Policy = toml::from_str(&format!(
+        "unsafe_modules = [\"*::raw\"]\nmax_unsafe_calls = 3\n[[allow]]\nid = \"{}\"",
+        get.id
+    ))
+    .unwrap();
+    assert!(policy.check(&graph).is_empty());
+}
+
+#[test]
+fn schema() {
+    let root = std::env::temp_dir().join(format!("upg-schema-{}", std::process::id()));
+    fs::create_dir_all(root.join("ui/app/lib")).unwrap();
+    schema::export(&root).unwrap();
+
+    // Generated files must match the checked-in ones, and vice versa.
+    let repo = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let list = |dir: &Path| {
+        let mut files: Vec<_> = fs::read_dir(dir.join("schema"))
+            .unwrap()
+            .map(|entry| Path::new("schema").join(entry.unwrap().file_name()))
+            .collect();
+        files.sort();
+        files.push("ui/app/lib/schema.ts".into());
+        files
+    };
+    let files = list(&root);
+    assert_eq!(files, list(repo));
+    for file in files {
+        assert!(
+            fs::read_to_string(root.join(&file)).unwrap()
+                == fs::read_to_string(repo.join(&file)).unwrap(),
+            "{file:?} is out of date, run `unsafety-propagation-graph --upg-export-schema=.`"
+        );
+    }
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn sqlite() {
+    let graph = analyze::source(SRC, &[], &Config::default(), &mut []).unwrap();
+    let dir = std::env::temp_dir().join(format!("upg-sqlite-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("upg.sqlite");
+    sqlite::write(&graph, &path).unwrap();
+
+    // Runs the example query in the module doc.
+    let doc = include_str!("../src/sqlite.rs");
+    let query: String = doc
+        .lines()
+        .skip_while(|line| *line != "//! ```sql")
+        .skip(1)
+        .take_while(|line| *line != "//! ```")
+        .map(|line| format!("{}\n", &line[4..]))
+        .collect();
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let mut rows: Vec<(String, String)> = conn
+        .prepare(&query)
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    rows.sort();
+    // `get` only reads `len` through `&self`.
+    assert_eq!(rows, [("buf::Buf::set_len".to_owned(), "len".to_owned())]);
+    drop(conn);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn datalog() {
+    let graph = analyze::source(SRC, &[], &Config::default(), &mut []).unwrap();
+    let dir = std::env::temp_dir().join(format!("upg-datalog-{}", std::process::id()));
+    Facts::new(&graph).write(&dir).unwrap();
+    let read = |relation: &str| -> Vec<Vec<String>> {
+        let facts = fs::read_to_string(dir.join(format!("{relation}.facts"))).unwrap();
+        let rows = facts.lines().map(|row| row.split('\t').map(String::from));
+        rows.map(Vec::from_iter).collect()
+    };
+    let names: HashMap<_, _> = read("fn_name")
+        .into_iter()
+        .chain(read("adt_name"))
+        .map(|row| (row[0].clone(), row[1].clone()))
+        .collect();
+    let set = |relation: &str| -> HashSet<String> {
+        read(relation)
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect()
+    };
+    let (public, unsafe_fn) = (set("public"), set("unsafe_fn"));
+
+    let access = read("access");
+    let is_write = read("is_write");
+    let named = |rows: &[Vec<String>]| -> Vec<[String; 3]> {
+        let rows = rows.iter().filter(|row| names[&row[1]] == "buf::Buf");
+        rows.map(|row| [names[&row[0]].clone(), row[2].clone(), row[3].clone()])
+            .collect()
+    };
+    let fact = |f: &str, field: &str, kind: &str| [f.to_owned(), field.to_owned(), kind.to_owned()];
+    assert_eq!(
+        named(&access),
+        [
+            fact("buf::Buf::set_len", "len", "MutDerefVariantField"),
+            fact("buf::Buf::set_len", "", "MutRef"),
+            fact("buf::Buf::as_slice", "ptr", "DerefVariantField"),
+            fact("buf::Buf::as_slice", "len", "DerefVariantField"),
+            fact("buf::Buf::as_slice", "", "Ref"),
+            fact("buf::Buf::get_unchecked", "ptr", "DerefVariantField"),
+            fact("buf::Buf::get_unchecked", "", "Ref"),
+            fact("buf::Buf::get", "len", "DerefVariantField"),
+            fact("buf::Buf::get", "", "Ref"),
+        ]
+    );
+    assert_eq!(
+        named(&is_write),
+        [
+            fact("buf::Buf::set_len", "len", "MutDerefVariantField"),
+            fact("buf::Buf::set_len", "", "MutRef"),
+        ]
+    );
+
+    // Soufflé isn't a dependency, so `field_read`, `field_write` and
+    // `safe_write_unsafe_read` in datalog/reports.dl are evaluated here.
+    let field_write = is_write.iter().filter(|row| !row[2].is_empty());
+    let field_read = access.iter().filter(|row| {
+        !row[2].is_empty() && row[3] != "ReinterpretUnionField" && !is_write.contains(row)
+    });
+    let field_read: Vec<_> = field_read.collect();
+    let mut reports = Vec::new();
+    for w in field_write.filter(|w| public.contains(&w[0]) && !unsafe_fn.contains(&w[0])) {
+        for r in &field_read {
+            if r[1..3] == w[1..3] && unsafe_fn.contains(&r[0]) {
+                reports.push([&names[&w[0]], &names[&w[1]], &w[2], &names[&r[0]]]);
+            }
+        }
+    }
+    assert_eq!(
+        reports,
+        [["buf::Buf::set_len", "buf::Buf", "len", "buf::Buf::as_slice"]]
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,57 @@
+//! `cargo upg` on a scratch package.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A package with a library in a new temporary directory.
+fn package(name: &str, lib: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("upg-{name}-{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    let manifest = format!("[package]\nname = \"{name}\"\nedition = \"2024\"\n\n[workspace]\n");
+    fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+    fs::write(dir.join("src/lib.rs"), lib).unwrap();
+    dir
+}
+
+fn cargo_upg(package: &Path, args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_cargo-upg"))
+        .arg("upg")
+        .args(args)
+        .arg("--quiet")
+        .current_dir(package)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn upg_dir() {
+    let package = package("upg_dir", "pub unsafe fn f() {}\n");
+    cargo_upg(&package, &["--upg-dir=out1"]);
+    // The checked crate is analyzed again for another directory.
+    cargo_upg(&package, &["--upg-dir=out2"]);
+    assert!(package.join("out2/upg_dir/summary.json").is_file());
+    assert!(package.join("out2/workspace.json").is_file());
+    fs::remove_dir_all(package).unwrap();
+}
+
+#[test]
+fn flags() {
+    let package = package("flags", "pub unsafe fn f() {}\n");
+    let crate_dir = package.join("out/flags");
+    fs::write(package.join("upg.toml"), "format = \"dot\"\n").unwrap();
+    cargo_upg(&package, &["--upg-dir=out"]);
+    assert!(crate_dir.join("graph.dot").is_file());
+
+    // Config files and flags changed after the crate is checked still apply.
+    fs::write(package.join("upg.toml"), "format = \"sqlite\"\n").unwrap();
+    cargo_upg(&package, &["--upg-dir=out"]);
+    assert!(crate_dir.join("upg.sqlite").is_file());
+    cargo_upg(&package, &["--upg-dir=out", "--upg-format=graphml"]);
+    assert!(crate_dir.join("graph.graphml").is_file());
+    fs::remove_dir_all(package).unwrap();
+}
@@ -0,0 +1,20 @@
+// compile-flags: --upg-format=ndjson --upg-no-mir --upg-no-src --upg-no-doc
+#![allow(dead_code)]
+
+pub fn f() -> i32 {
+    let g = |x: i32| x + 1;
+    g(1)
+}
+
+pub fn call_unsafe(ptr: *const i32) -> i32 {
+    let read = |p: *const i32| unsafe { *p };
+    read(ptr)
+}
+
+pub fn call_boxed(f: Box<dyn Fn() -> i32>) -> i32 {
+    f()
+}
+
+pub fn drop_string(ptr: *mut String) {
+    unsafe { std::ptr::drop_in_place(ptr) }
+}
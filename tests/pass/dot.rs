@@ -0,0 +1,45 @@
+// revisions: dot graphml ndjson
+//[dot] compile-flags: --upg-format=dot
+//[graphml] compile-flags: --upg-format=graphml
+//[ndjson] compile-flags: --upg-format=ndjson --upg-no-mir --upg-no-src --upg-no-doc
+// The fixture of output formats, also analyzed by tests/api.rs.
+#![allow(dead_code)]
+
+pub mod buf {
+    pub struct Buf {
+        ptr: *mut u8,
+        len: usize,
+    }
+
+    impl Buf {
+        pub fn set_len(&mut self, len: usize) {
+            self.len = len;
+        }
+
+        pub unsafe fn as_slice(&self) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+
+        pub unsafe fn get_unchecked(&self, idx: usize) -> u8 {
+            unsafe { *self.ptr.add(idx) }
+        }
+
+        pub fn get(&self, idx: usize) -> Option<u8> {
+            if idx < self.len {
+                Some(unsafe { self.get_unchecked(idx) })
+            } else {
+                None
+            }
+        }
+    }
+}
+
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn to_bits(f: f32) -> u32 {
+    let bits = Bits { float: f };
+    unsafe { bits.int }
+}
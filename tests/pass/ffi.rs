@@ -0,0 +1,22 @@
+#![allow(dead_code)]
+
+unsafe extern "C" {
+    /// Computes the absolute value.
+    safe fn abs(x: i32) -> i32;
+
+    #[link_name = "strlen"]
+    fn c_strlen(s: *const u8) -> usize;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn upg_abs(x: i32) -> i32 {
+    abs(x)
+}
+
+#[unsafe(export_name = "upg_strlen")]
+pub unsafe extern "C" fn strlen(s: *const u8) -> usize {
+    unsafe { c_strlen(s) }
+}
+
+#[unsafe(no_mangle)]
+pub fn rust_abi() {}
@@ -0,0 +1,66 @@
+// compile-flags: --upg-format=dot --upg-lints
+#![allow(dead_code)]
+
+/// Reads the byte at `ptr`.
+unsafe fn read(ptr: *const u8) -> u8 {
+    unsafe { *ptr }
+}
+
+/// # Safety
+///
+/// `ptr` must be valid for reads.
+unsafe fn read_documented(ptr: *const u8) -> u8 {
+    unsafe { *ptr }
+}
+
+fn first(bytes: &[u8]) -> u8 {
+    unsafe { read(bytes.as_ptr()) }
+}
+
+#[cfg_attr(upg, allow(upg::undocumented_unsafe_call))]
+fn first_allowed(bytes: &[u8]) -> u8 {
+    unsafe { read(bytes.as_ptr()) }
+}
+
+fn first_documented(bytes: &[u8]) -> u8 {
+    unsafe { read_documented(bytes.as_ptr()) }
+}
+
+struct Cursor {
+    data: *const u8,
+    pos: usize,
+}
+
+impl Cursor {
+    /// # Safety
+    ///
+    /// `pos` must be in bounds of `data`.
+    unsafe fn peek(&self) -> u8 {
+        unsafe { read_documented(self.data.add(self.pos)) }
+    }
+
+    fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    #[allow(upg::safe_critical_field_write)]
+    fn rewind(&mut self) {
+        self.pos = 0;
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn is_null(&self) -> bool {
+        self.data.is_null()
+    }
+}
+
+/// # Safety
+///
+/// None.
+unsafe fn nothing() {}
+
+#[cfg_attr(upg, allow(upg::unnecessary_unsafe_fn))]
+unsafe fn nothing_allowed() {}
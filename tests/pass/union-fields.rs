@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+/// Union doc.
+union U {
+    /// Field int doc.
+    int: u32,
+    /// Field float doc.
+    float: f32,
+}
+
+impl U {
+    fn new(int: u32) -> U {
+        U { int }
+    }
+
+    unsafe fn int(&self) -> u32 {
+        self.int
+    }
+
+    fn set_float(&mut self, float: f32) {
+        self.float = float;
+    }
+}
+
+fn reinterpret(int: u32) -> f32 {
+    let mut u = U::new(int);
+    u.float = 1.0;
+    unsafe { u.float + f32::from_bits(u.int) }
+}
+
+union Same {
+    a: u32,
+    b: u32,
+}
+
+/// `b` is read before `a` is written, so it's not reinterpreted.
+fn copy_field(mut s: Same) -> Same {
+    s.a = unsafe { s.b };
+    s
+}
+
+/// Initializing the union writes `int`, so reading `float` reinterprets it.
+fn init_field(int: u32) -> f32 {
+    let u = U { int };
+    unsafe { u.float }
+}
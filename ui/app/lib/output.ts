@@ -1,17 +1,10 @@
+// Output types are generated from the driver. See `schema.ts`.
+export * from "./schema"
 
-export type Function = {
-  name: string,
-  safe: boolean,
-  callees: string[],
-  adts: { [key: string]: string[] },
-  span: string,
-  src: string,
-  mir: string,
-  doc: string,
-}
+import type { Function } from "./schema"
+import { SCHEMA_VERSION } from "./schema"
 
 export const EMPTY_FUNCTION: Function = {
-  name: "", safe: true, callees: [], adts: {}, span: "",
-  src: "", mir: "", doc: "",
+  schema_version: SCHEMA_VERSION, id: "", name: "", module: "", safe: true, public: false, callees: [], calls: [],
+  adts: {}, span: "", src: "", mir: "", doc: "",
 };
-
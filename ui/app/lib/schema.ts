@@ -0,0 +1,322 @@
+// Generated from the output types by `unsafety-propagation-graph --upg-export-schema`.
+// Don't edit by hand.
+
+export const SCHEMA_VERSION = 1;
+
+export type CallSite = {
+  /**
+   * The callee ID.
+   */
+  callee: string,
+  span: string,
+  /**
+   * The call is inside an `unsafe {}` block written in the function.
+   */
+  unsafe_block: boolean,
+  /**
+   * The callee is an unsafe fn. Recorded at the call, because the callee
+   * may not be emitted.
+   */
+  unsafe_callee: boolean,
+};
+
+/**
+ * How a function accesses an adt or its field.
+ */
+export type AdtAccess = {
+  kind: "Ref",
+} | {
+  kind: "MutRef",
+} | {
+  kind: "Deref",
+} | {
+  kind: "MutDeref",
+} | {
+  kind: "Plain",
+} | (VariantFieldRef & {
+  kind: "RefVariantField",
+}) | (VariantFieldRef & {
+  kind: "MutRefVariantField",
+}) | (VariantFieldRef & {
+  kind: "DerefVariantField",
+}) | (VariantFieldRef & {
+  kind: "MutDerefVariantField",
+}) | (VariantFieldRef & {
+  kind: "ReadUnionField",
+}) | (VariantFieldRef & {
+  kind: "WriteUnionField",
+}) | (VariantFieldRef & {
+  kind: "ReinterpretUnionField",
+}) | {
+  /**
+   * A compact projection path like `*.0@1[_3]`:
+   * * `*` deref
+   * * `.N` field
+   * * `@N` downcast to variant
+   * * `[_N]` index by a local, `[N]` and `[-N]` constant index from start and end
+   * * `[A..B]` and `[A..-B]` subslice
+   * * `@opaque` opaque cast
+   */
+  projection: string,
+  kind: "Unknown",
+};
+
+/**
+ * A variant or field with indices and resolved names. `variant` is null for
+ * struct or union fields, and `field` is null for enum variants.
+ */
+export type VariantFieldRef = {
+  variant: number | null,
+  field: number | null,
+  variant_name?: string | null,
+  field_name?: string | null,
+};
+
+export type UnsafeOp = {
+  /**
+   * The adt ID.
+   */
+  adt: string,
+  field: string,
+  reinterpret: boolean,
+  span: string,
+  kind: "UnionFieldRead",
+};
+
+/**
+ * The foreign function interface of a function.
+ */
+export type Ffi = {
+  kind: FfiKind,
+  /**
+   * The ABI string like `C` or `system`.
+   */
+  abi: string,
+  /**
+   * The symbol to link against or export as.
+   */
+  link_name: string,
+};
+
+export type FfiKind = "Import" | "Export";
+
+export type Function = {
+  schema_version: number,
+  /**
+   * The stable node ID, which is also the file stem. See [`crate::id`].
+   */
+  id: string,
+  name: string,
+  /**
+   * The path of the module containing the function.
+   */
+  module: string,
+  safe: boolean,
+  /**
+   * Reachable from other crates.
+   */
+  public: boolean,
+  /**
+   * IDs of callees.
+   */
+  callees: string[],
+  /**
+   * Direct calls in the order of call terminators.
+   */
+  calls: CallSite[],
+  /**
+   * Accesses keyed by adt IDs.
+   */
+  adts: {
+    [key: string]: AdtAccess[],
+  },
+  unsafe_ops?: UnsafeOp[],
+  ffi?: Ffi | null,
+  span: string,
+  src: string,
+  mir: string,
+  doc: string,
+  /**
+   * Results of custom passes keyed by pass names. See [`crate::pass`].
+   */
+  passes?: {
+    [key: string]: unknown,
+  },
+};
+
+export type Access = {
+  read: string[],
+  write: string[],
+  other: string[],
+  reinterpret?: string[],
+};
+
+export type VariantField = {
+  variant: number | null,
+  field: number | null,
+  name: string,
+  doc: string,
+};
+
+export type Adt = {
+  schema_version: number,
+  id: string,
+  name: string,
+  module: string,
+  /**
+   * Function IDs here and in accesses.
+   */
+  constructors: string[],
+  access_self_as_arg: Access,
+  access_self_as_locals: Access,
+  access_field: Access[],
+  span: string,
+  src: string,
+  kind: string,
+  doc_adt: string,
+  variant_fields: {
+    [key: string]: VariantField,
+  },
+  /**
+   * Results of custom passes keyed by pass names. See [`crate::pass`].
+   */
+  passes?: {
+    [key: string]: unknown,
+  },
+};
+
+export type FnSummary = {
+  name: string,
+  safe: boolean,
+  /**
+   * Keys of direct callees, which may live in other crates.
+   */
+  callees: string[],
+  /**
+   * The function is unsafe, or transitively calls an unsafe function.
+   */
+  reach_unsafe: boolean,
+};
+
+/**
+ * A lightweight node for a callee outside the local crate. The callee is not
+ * analyzed, but the graph is navigable one hop past the crate boundary.
+ */
+export type Stub = {
+  schema_version: number,
+  id: string,
+  name: string,
+  module: string,
+  /**
+   * The key in the summary of the dependency crate, i.e. the def path hash.
+   */
+  key: string,
+  crate: string,
+  safe: boolean,
+  /**
+   * The monomorphized signature.
+   */
+  sig: string,
+  intrinsic: boolean,
+  doc: string,
+  /**
+   * The summary from the dependency crate if it has been analyzed.
+   */
+  summary?: FnSummary | null,
+  /**
+   * Results of custom passes keyed by pass names. See [`crate::pass`].
+   */
+  passes?: {
+    [key: string]: unknown,
+  },
+};
+
+/**
+ * A compact per-crate summary written to `dir/crate_name/summary.json`.
+ *
+ * When a later crate is analyzed, the summaries of its dependencies are loaded
+ * to continue callee edges and transitive unsafety past the crate boundary.
+ */
+export type Summary = {
+  schema_version: number,
+  crate: string,
+  /**
+   * Functions with bodies, keyed by def path hashes.
+   */
+  functions: {
+    [key: string]: FnSummary,
+  },
+};
+
+export type IndexCounts = {
+  functions: number,
+  unsafe_functions: number,
+  adts: number,
+  stubs: number,
+};
+
+export type IndexFunction = {
+  id: string,
+  name: string,
+  /**
+   * The file path relative to the crate directory.
+   */
+  path: string,
+  safe: boolean,
+  span: string,
+  callees: number,
+  adts: number,
+  unsafe_ops: number,
+};
+
+export type IndexAdt = {
+  id: string,
+  name: string,
+  path: string,
+  kind: string,
+  span: string,
+  constructors: number,
+  /**
+   * The number of functions accessing the adt or its fields.
+   */
+  accesses: number,
+};
+
+export type IndexStub = {
+  id: string,
+  name: string,
+  path: string,
+  crate: string,
+  safe: boolean,
+};
+
+/**
+ * The manifest of a crate written to `index.json`, so that front-ends and
+ * scripts can discover the data without listing directories.
+ */
+export type Index = {
+  schema_version: number,
+  crate: string,
+  toolchain: string,
+  /**
+   * Seconds since the Unix epoch.
+   */
+  timestamp: number,
+  counts: IndexCounts,
+  functions: IndexFunction[],
+  adts: IndexAdt[],
+  stubs: IndexStub[],
+};
+
+/**
+ * Display names of nodes and edge targets written to `names.json`.
+ */
+export type Names = {
+  schema_version: number,
+  /**
+   * Names keyed by IDs.
+   */
+  names: {
+    [key: string]: string,
+  },
+};